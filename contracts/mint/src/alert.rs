@@ -0,0 +1,98 @@
+use cosmwasm_std::{
+    attr, Decimal, Deps, DepsMut, MessageInfo, Response, StdError, StdResult, Uint128,
+};
+use melange_protocol::{
+    attributes::{ACTION, OWNER, POSITION_IDX},
+    common::{PageRequest, PageResponse},
+    mint::{TriggeredAlert, TriggeredAlertsResponse},
+};
+
+use crate::{
+    context::{Context, PriceFeed},
+    math::{decimal_division, decimal_multiplication},
+    state::{
+        read_position, read_position_alerts, remove_position_alert, store_position_alert,
+        AlertScan, Position,
+    },
+};
+
+pub fn set_alert(
+    deps: DepsMut,
+    info: MessageInfo,
+    position_idx: Uint128,
+    ratio: Option<Decimal>,
+) -> StdResult<Response> {
+    let position: Position = read_position(deps.storage, position_idx)?;
+    if position.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut attributes = vec![
+        attr(ACTION, "set_alert"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr(OWNER, info.sender),
+    ];
+    match ratio {
+        Some(ratio) if ratio.is_zero() => {
+            return Err(StdError::generic_err("Alert ratio must be positive"));
+        }
+        Some(ratio) => {
+            store_position_alert(deps.storage, position_idx, ratio)?;
+            attributes.push(attr("alert_ratio", ratio.to_string()));
+        }
+        None => remove_position_alert(deps.storage, position_idx),
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+pub fn query_triggered_alerts(
+    deps: Deps,
+    page: Option<PageRequest>,
+) -> StdResult<TriggeredAlertsResponse> {
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Liquidation);
+    let page = page.unwrap_or_default();
+    let limit = ctx.config.query_limits.triggered_alerts.resolve(page.limit);
+    let start_after = page.parse_cursor()?;
+
+    let scan: AlertScan<TriggeredAlert> = read_position_alerts(
+        deps.storage,
+        start_after,
+        limit,
+        page.order,
+        |idx, alert_ratio| {
+            let position: Position = read_position(deps.storage, idx)?;
+            if position.asset.amount.is_zero() {
+                return Ok(None);
+            }
+
+            let (asset_price, (collateral_price, _, _)) =
+                ctx.prices(deps, &position.asset.info, &position.collateral.info, false)?;
+            let collateral_ratio = decimal_multiplication(
+                Decimal::from_ratio(position.collateral.amount, position.asset.amount),
+                decimal_division(collateral_price, asset_price),
+            );
+            if collateral_ratio >= alert_ratio {
+                return Ok(None);
+            }
+
+            Ok(Some(TriggeredAlert {
+                position_idx: idx,
+                owner: deps.api.addr_humanize(&position.owner)?.to_string(),
+                alert_ratio,
+                collateral_ratio,
+            }))
+        },
+    )?;
+
+    // the cursor is the last alert visited, so a page of healthy positions still advances
+    let page = if scan.truncated {
+        PageResponse::truncated(scan.last_idx)
+    } else {
+        PageResponse::new(scan.last_idx, scan.alerts.len(), limit)
+    };
+    Ok(TriggeredAlertsResponse {
+        alerts: scan.alerts,
+        page,
+    })
+}
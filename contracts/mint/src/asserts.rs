@@ -2,10 +2,16 @@ use std::str::FromStr;
 
 use crate::{
     contract::MIN_CR_ALLOWED,
-    state::{AssetConfig, Position},
+    state::{
+        is_integrator, read_collateral_cap, read_config, read_frozen_position,
+        read_integrator_allowlist_enabled, read_total_collateral, AssetConfig, Config, Position,
+    },
+};
+use cosmwasm_std::{Addr, Decimal, Deps, Env, MessageInfo, StdError, StdResult, Storage, Uint128};
+use melange_protocol::{
+    asset::{Asset, AssetInfoRaw},
+    mint::FeeDiscountTier,
 };
-use cosmwasm_std::{Decimal, Deps, Env, StdError, StdResult};
-use melange_protocol::asset::Asset;
 
 pub fn assert_revoked_collateral(
     load_collateral_res: (Decimal, Decimal, bool),
@@ -50,3 +56,167 @@ pub fn assert_asset(deps: Deps, position: &Position, asset: &Asset) -> StdResult
 
     Ok(())
 }
+
+// Check tiers are sorted by stake threshold and only ever discount the fee
+pub fn assert_fee_discount_tiers(tiers: &[FeeDiscountTier]) -> StdResult<()> {
+    for (i, tier) in tiers.iter().enumerate() {
+        if tier.fee_multiplier > Decimal::one() {
+            return Err(StdError::generic_err(
+                "fee_multiplier must be less than or equal to 1",
+            ));
+        }
+
+        if i > 0 && tier.min_staked <= tiers[i - 1].min_staked {
+            return Err(StdError::generic_err(
+                "fee discount tiers must be sorted by ascending min_staked",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn assert_referral_fee_rate(rate: Decimal) -> StdResult<Decimal> {
+    if rate > Decimal::one() {
+        Err(StdError::generic_err("referral_fee_rate must be 0 to 1"))
+    } else {
+        Ok(rate)
+    }
+}
+
+pub fn assert_flash_mint_fee_rate(rate: Decimal) -> StdResult<Decimal> {
+    if rate > Decimal::one() {
+        Err(StdError::generic_err("flash_mint_fee_rate must be 0 to 1"))
+    } else {
+        Ok(rate)
+    }
+}
+
+pub fn assert_asset_fee_rate(rate: Decimal) -> StdResult<Decimal> {
+    if rate >= Decimal::one() {
+        Err(StdError::generic_err("Asset fee rates must be less than 1"))
+    } else {
+        Ok(rate)
+    }
+}
+
+// Check quote denoms are distinct and leave base_denom out, it is always accepted
+pub fn assert_quote_denoms(base_denom: &str, quote_denoms: Vec<String>) -> StdResult<Vec<String>> {
+    for (i, denom) in quote_denoms.iter().enumerate() {
+        if denom == base_denom {
+            return Err(StdError::generic_err(
+                "base_denom is always a quote denom and cannot be listed",
+            ));
+        }
+        if quote_denoms[..i].contains(denom) {
+            return Err(StdError::generic_err(format!(
+                "Quote denom {} is listed twice",
+                denom
+            )));
+        }
+    }
+
+    Ok(quote_denoms)
+}
+
+// Protocol fees charged in a quote denom are sold for base_denom on the dex
+pub fn assert_quote_denoms_sellable(config: &Config) -> StdResult<()> {
+    if !config.quote_denoms.is_empty() && config.dex_contract.is_none() {
+        return Err(StdError::generic_err(
+            "Quote denoms require a dex contract to sell their protocol fees",
+        ));
+    }
+
+    Ok(())
+}
+
+/// While the integrator allow-list is enabled, contracts opening positions must be on it.
+/// Only senders the chain knows as contracts are checked, accounts are unaffected
+pub fn assert_integrator(deps: Deps, sender: &Addr) -> StdResult<()> {
+    if !read_integrator_allowlist_enabled(deps.storage)? {
+        return Ok(());
+    }
+    if deps.querier.query_wasm_contract_info(sender).is_err() {
+        return Ok(());
+    }
+    if !is_integrator(deps.storage, &deps.api.addr_canonicalize(sender.as_str())?) {
+        return Err(StdError::generic_err(
+            "Contracts must be allow-listed integrators to open positions",
+        ));
+    }
+    Ok(())
+}
+
+// while the totals are being backfilled they undercount, and the cap binds less
+pub fn assert_collateral_cap(
+    storage: &dyn Storage,
+    collateral: &AssetInfoRaw,
+    amount: Uint128,
+) -> StdResult<()> {
+    if let Some(cap) = read_collateral_cap(storage, collateral)? {
+        if read_total_collateral(storage, collateral)?.checked_add(amount)? > cap {
+            return Err(StdError::generic_err(
+                "Deposit exceeds the cap of the collateral",
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn assert_owner_or_guardian(deps: Deps, info: &MessageInfo) -> StdResult<()> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != config.owner && Some(sender_raw) != config.guardian {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    Ok(())
+}
+
+pub fn assert_mel_fee_discount(discount: Decimal) -> StdResult<Decimal> {
+    if discount >= Decimal::one() {
+        Err(StdError::generic_err(
+            "mel_fee_discount must be less than 1",
+        ))
+    } else {
+        Ok(discount)
+    }
+}
+
+pub fn assert_not_frozen(storage: &dyn Storage, position_idx: Uint128) -> StdResult<()> {
+    match read_frozen_position(storage, position_idx)? {
+        Some(frozen_position) => Err(StdError::generic_err(format!(
+            "Position is frozen: {}",
+            frozen_position.reason
+        ))),
+        None => Ok(()),
+    }
+}
+
+const MAX_POSITION_LABEL_LENGTH: usize = 32;
+
+pub fn assert_position_label(label: &str) -> StdResult<()> {
+    if label.is_empty() || label.chars().count() > MAX_POSITION_LABEL_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Position label must be 1 to {} characters",
+            MAX_POSITION_LABEL_LENGTH
+        )));
+    }
+    if label.chars().any(char::is_control) {
+        return Err(StdError::generic_err(
+            "Position label must not contain control characters",
+        ));
+    }
+
+    Ok(())
+}
+
+// rejects price-sensitive executions left in the mempool past the deadline the sender set
+pub fn assert_deadline(env: &Env, deadline: Option<u64>) -> StdResult<()> {
+    match deadline {
+        Some(deadline) if env.block.time.seconds() > deadline => {
+            Err(StdError::generic_err("Deadline exceeded"))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,149 @@
+use cosmwasm_std::{
+    attr, Addr, Api, Decimal, Deps, DepsMut, MessageInfo, Response, StdError, StdResult,
+};
+use melange_protocol::{
+    asset::AssetInfo, attributes::ACTION, mint::BasketComponent as BasketComponentResponse,
+};
+
+use crate::{
+    querier::query_prices,
+    state::{
+        read_asset_config, read_asset_configs, read_config, store_asset_config, AssetConfig,
+        BasketComponent, Config,
+    },
+};
+
+pub fn update_asset_basket(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_token: String,
+    basket: Option<Vec<BasketComponentResponse>>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let mut asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+
+    let mut attributes = vec![
+        attr(ACTION, "update_asset_basket"),
+        attr("asset_token", asset_token),
+    ];
+    asset_config.basket = match basket {
+        Some(basket) => {
+            if basket.is_empty() {
+                return Err(StdError::generic_err("Basket must have a component"));
+            }
+            if asset_config.price_feeds.is_some() {
+                return Err(StdError::generic_err("Asset is priced by dual price feeds"));
+            }
+
+            // baskets do not nest, so a price never takes more than one Prices query
+            if read_asset_configs(deps.storage)?.iter().any(|other| {
+                other.basket.as_ref().is_some_and(|other_basket| {
+                    other_basket
+                        .iter()
+                        .any(|component| component.asset_token == asset_token_raw)
+                })
+            }) {
+                return Err(StdError::generic_err(
+                    "Asset is a component of another basket",
+                ));
+            }
+
+            let mut components: Vec<BasketComponent> = vec![];
+            for component in basket {
+                let component_raw = deps.api.addr_canonicalize(&component.asset_token)?;
+                if component_raw == asset_token_raw {
+                    return Err(StdError::generic_err(
+                        "Basket cannot contain the asset itself",
+                    ));
+                }
+                if components
+                    .iter()
+                    .any(|other| other.asset_token == component_raw)
+                {
+                    return Err(StdError::generic_err(format!(
+                        "Basket component {} is listed twice",
+                        component.asset_token
+                    )));
+                }
+                if component.weight.is_zero() {
+                    return Err(StdError::generic_err("Basket weights must be positive"));
+                }
+
+                let component_config: AssetConfig =
+                    read_asset_config(deps.storage, &component_raw)?;
+                if component_config.basket.is_some() {
+                    return Err(StdError::generic_err(format!(
+                        "Basket component {} is a basket",
+                        component.asset_token
+                    )));
+                }
+                if component_config.end_price.is_some() {
+                    return Err(StdError::generic_err(format!(
+                        "Basket component {} is delisted",
+                        component.asset_token
+                    )));
+                }
+
+                attributes.push(attr(
+                    "component",
+                    format!("{}:{}", component.asset_token, component.weight),
+                ));
+                components.push(BasketComponent {
+                    asset_token: component_raw,
+                    weight: component.weight,
+                });
+            }
+            Some(components)
+        }
+        None => None,
+    };
+    store_asset_config(deps.storage, &asset_token_raw, &asset_config)?;
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// Price of a basket asset, the weighted sum of its component prices read with a single
+/// Prices query to the collateral oracle
+pub fn basket_price(
+    deps: Deps,
+    config: &Config,
+    basket: &[BasketComponent],
+    check_expire: bool,
+) -> StdResult<Decimal> {
+    let assets = basket
+        .iter()
+        .map(|component| {
+            Ok(AssetInfo::Token {
+                contract_addr: deps.api.addr_humanize(&component.asset_token)?.to_string(),
+            })
+        })
+        .collect::<StdResult<Vec<AssetInfo>>>()?;
+    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
+    let prices = query_prices(&deps.querier, collateral_oracle, assets, check_expire)?;
+
+    let mut price = Decimal::zero();
+    for (component, component_price) in basket.iter().zip(prices) {
+        price = price.checked_add(component.weight * component_price.rate)?;
+    }
+    Ok(price)
+}
+
+pub fn basket_to_normal(
+    api: &dyn Api,
+    basket: &[BasketComponent],
+) -> StdResult<Vec<BasketComponentResponse>> {
+    basket
+        .iter()
+        .map(|component| {
+            Ok(BasketComponentResponse {
+                asset_token: api.addr_humanize(&component.asset_token)?.to_string(),
+                weight: component.weight,
+            })
+        })
+        .collect()
+}
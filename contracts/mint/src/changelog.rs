@@ -0,0 +1,90 @@
+use cosmwasm_std::{to_binary, Binary, CanonicalAddr, Deps, Env, StdResult, Storage};
+use melange_protocol::{
+    common::{PageRequest, PageResponse},
+    mint::{AdminActionResponse, AdminActionsResponse, ExecuteMsg, MigrateMsg},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::state::{append_admin_action, read_admin_actions, read_config, AdminAction, Config};
+
+/// Name under which an execute message is recorded in the changelog, none for user actions.
+/// Every message only the owner, the guardian or governance may send is listed here
+pub fn admin_action_name(msg: &ExecuteMsg) -> Option<&'static str> {
+    match msg {
+        ExecuteMsg::UpdateConfig { .. } => Some("update_config"),
+        ExecuteMsg::UpdateAsset { .. } => Some("update_asset"),
+        ExecuteMsg::UpdateAssetBasket { .. } => Some("update_asset_basket"),
+        ExecuteMsg::UpdateAssetPriceFeeds { .. } => Some("update_asset_price_feeds"),
+        ExecuteMsg::UpdateCollateralCap { .. } => Some("update_collateral_cap"),
+        ExecuteMsg::RegisterAsset { .. } => Some("register_asset"),
+        ExecuteMsg::RegisterMigration { .. } => Some("register_migration"),
+        ExecuteMsg::UpdateFeeDiscountTiers { .. } => Some("update_fee_discount_tiers"),
+        ExecuteMsg::UpdateIntegrators { .. } => Some("update_integrators"),
+        ExecuteMsg::UpdateIbcCounterpartyPorts { .. } => Some("update_ibc_counterparty_ports"),
+        ExecuteMsg::CheckSupply { .. } => Some("check_supply"),
+        ExecuteMsg::UnpauseMint { .. } => Some("unpause_mint"),
+        ExecuteMsg::FreezePosition { .. } => Some("freeze_position"),
+        ExecuteMsg::UnfreezePosition { .. } => Some("unfreeze_position"),
+        _ => None,
+    }
+}
+
+pub fn migrate_action_name(msg: &MigrateMsg) -> &'static str {
+    match msg {
+        MigrateMsg::RewriteStorage { .. } => "rewrite_storage",
+        MigrateMsg::BackfillTotals { .. } => "backfill_totals",
+    }
+}
+
+pub fn record_admin_action<T: Serialize>(
+    storage: &mut dyn Storage,
+    env: &Env,
+    actor: CanonicalAddr,
+    action: &str,
+    msg: &T,
+) -> StdResult<()> {
+    let payload: Binary = to_binary(msg)?;
+    let payload_hash = Sha256::digest(payload.as_slice())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    append_admin_action(
+        storage,
+        &AdminAction {
+            height: env.block.height,
+            time: env.block.time.seconds(),
+            actor,
+            action: action.to_string(),
+            payload_hash,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn query_admin_actions(
+    deps: Deps,
+    page: Option<PageRequest>,
+) -> StdResult<AdminActionsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let page = page.unwrap_or_default();
+    let limit = config.query_limits.admin_actions.resolve(page.limit);
+
+    let actions = read_admin_actions(deps.storage, page.parse_cursor()?, limit, page.order)?
+        .into_iter()
+        .map(|(id, admin_action)| {
+            Ok(AdminActionResponse {
+                id,
+                height: admin_action.height,
+                time: admin_action.time,
+                actor: deps.api.addr_humanize(&admin_action.actor)?.to_string(),
+                action: admin_action.action,
+                payload_hash: admin_action.payload_hash,
+            })
+        })
+        .collect::<StdResult<Vec<AdminActionResponse>>>()?;
+    let page = PageResponse::new(actions.last().map(|action| action.id), actions.len(), limit);
+
+    Ok(AdminActionsResponse { actions, page })
+}
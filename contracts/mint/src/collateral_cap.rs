@@ -0,0 +1,50 @@
+use cosmwasm_std::{attr, Deps, DepsMut, MessageInfo, Response, StdError, StdResult, Uint128};
+use melange_protocol::{
+    asset::{AssetInfo, AssetInfoRaw},
+    attributes::ACTION,
+    mint::CollateralCapResponse,
+};
+
+use crate::state::{
+    read_asset_totals_complete, read_collateral_cap, read_config, read_total_collateral,
+    remove_collateral_cap, store_collateral_cap, Config,
+};
+
+pub fn update_collateral_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    collateral: AssetInfo,
+    cap: Option<Uint128>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let collateral_raw: AssetInfoRaw = collateral.to_raw(deps.api)?;
+    let mut attributes = vec![
+        attr(ACTION, "update_collateral_cap"),
+        attr("collateral", collateral.to_string()),
+    ];
+    // lowering the cap below the current total only blocks further deposits
+    match cap {
+        Some(cap) => {
+            store_collateral_cap(deps.storage, &collateral_raw, cap)?;
+            attributes.push(attr("cap", cap.to_string()));
+        }
+        None => remove_collateral_cap(deps.storage, &collateral_raw),
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+pub fn query_collateral_cap(deps: Deps, collateral: AssetInfo) -> StdResult<CollateralCapResponse> {
+    let collateral_raw: AssetInfoRaw = collateral.to_raw(deps.api)?;
+
+    Ok(CollateralCapResponse {
+        cap: read_collateral_cap(deps.storage, &collateral_raw)?,
+        total_collateral: read_total_collateral(deps.storage, &collateral_raw)?,
+        totals_complete: read_asset_totals_complete(deps.storage)?,
+        collateral,
+    })
+}
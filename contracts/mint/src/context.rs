@@ -0,0 +1,293 @@
+use cosmwasm_std::{Addr, CanonicalAddr, Decimal, Deps, StdError, StdResult, Storage};
+use melange_protocol::asset::{AssetInfo, AssetInfoRaw};
+
+use crate::{
+    basket::basket_price,
+    querier::{load_asset_price, load_collateral_info, query_price, query_prices},
+    state::{may_read_asset_config, read_config, AssetConfig, BasketComponent, Config, Position},
+};
+
+/// Which feed prices assets configured with dual price feeds. Handlers checking a mint
+/// against the minimum collateral ratio use `Mint`, handlers looking for underwater
+/// positions use `Liquidation`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceFeed {
+    Spot,
+    Mint,
+    Liquidation,
+}
+
+/// Config, asset configs and oracle responses loaded at most once per execution.
+/// Handlers create one context and pass it to everything they call
+pub struct Context {
+    pub config: Config,
+    price_feed: PriceFeed,
+    asset_configs: Vec<(CanonicalAddr, Option<AssetConfig>)>,
+    asset_prices: Vec<(AssetInfoRaw, bool, Decimal)>,
+    collateral_infos: Vec<(AssetInfoRaw, bool, (Decimal, Decimal, bool))>,
+}
+
+impl Context {
+    pub fn load(storage: &dyn Storage) -> StdResult<Context> {
+        Ok(Context {
+            config: read_config(storage)?,
+            price_feed: PriceFeed::Spot,
+            asset_configs: vec![],
+            asset_prices: vec![],
+            collateral_infos: vec![],
+        })
+    }
+
+    /// Prices read afterwards come from `price_feed`; cached prices are not tagged with
+    /// their feed, so this is set right after loading
+    pub fn with_price_feed(mut self, price_feed: PriceFeed) -> Context {
+        self.price_feed = price_feed;
+        self
+    }
+
+    pub fn asset_config(
+        &mut self,
+        storage: &dyn Storage,
+        asset_token: &CanonicalAddr,
+    ) -> StdResult<AssetConfig> {
+        self.load_asset_config(storage, asset_token)?
+            .ok_or_else(|| StdError::generic_err("no asset data stored"))
+    }
+
+    pub fn position_asset_config(
+        &mut self,
+        storage: &dyn Storage,
+        position: &Position,
+    ) -> StdResult<AssetConfig> {
+        match &position.asset.info {
+            AssetInfoRaw::Token { contract_addr } => self.asset_config(storage, contract_addr),
+            _ => Err(StdError::generic_err("Asset must be a token")),
+        }
+    }
+
+    // loads the asset price, the stored end_price for migrated assets, the weighted
+    // component prices for basket assets and the selected feed for dual priced assets
+    pub fn asset_price(
+        &mut self,
+        deps: Deps,
+        asset: &AssetInfoRaw,
+        check_expire: bool,
+    ) -> StdResult<Decimal> {
+        if let Some(price) = self.cached_asset_price(asset, check_expire) {
+            return Ok(price);
+        }
+
+        let end_price = self.end_price(deps.storage, asset)?;
+        let basket = self.basket(deps.storage, asset)?;
+        let dual_price_feed = self.dual_price_feed(deps.storage, asset)?;
+        let price = match (end_price, basket, dual_price_feed) {
+            (None, Some(basket), _) => basket_price(deps, &self.config, &basket, check_expire)?,
+            (None, None, Some(feed)) => query_price(
+                &deps.querier,
+                deps.api.addr_humanize(&self.config.oracle)?,
+                feed,
+                None,
+                check_expire,
+            )?,
+            _ => load_asset_price(deps, &self.config, asset, end_price, check_expire)?,
+        };
+        self.asset_prices.push((asset.clone(), check_expire, price));
+        Ok(price)
+    }
+
+    // loads (price, multiplier, is_revoked) of the collateral
+    pub fn collateral_info(
+        &mut self,
+        deps: Deps,
+        collateral: &AssetInfoRaw,
+        check_expire: bool,
+    ) -> StdResult<(Decimal, Decimal, bool)> {
+        if let Some(collateral_info) = self.cached_collateral_info(collateral, check_expire) {
+            return Ok(collateral_info);
+        }
+
+        let end_price = self.end_price(deps.storage, collateral)?;
+        let collateral_info =
+            load_collateral_info(deps, &self.config, collateral, end_price, check_expire)?;
+        self.collateral_infos
+            .push((collateral.clone(), check_expire, collateral_info));
+        Ok(collateral_info)
+    }
+
+    /// Price of one unit of a base denom in `base_denom`: one for `base_denom` itself,
+    /// the collateral oracle cross rate for the quote denoms
+    pub fn quote_rate(
+        &mut self,
+        deps: Deps,
+        denom: &str,
+        check_expire: bool,
+    ) -> StdResult<Decimal> {
+        if denom == self.config.base_denom {
+            return Ok(Decimal::one());
+        }
+        if !self.config.quote_denoms.contains(&denom.to_string()) {
+            return Err(StdError::generic_err(format!(
+                "{} is not a base denom",
+                denom
+            )));
+        }
+
+        let denom = AssetInfoRaw::NativeToken {
+            denom: denom.to_string(),
+        };
+        let (rate, _, _) = self.collateral_info(deps, &denom, check_expire)?;
+        Ok(rate)
+    }
+
+    /// Loads the asset price and the collateral info together. Whatever is neither cached
+    /// nor resolved locally is fetched with a single Prices query to the collateral oracle
+    pub fn prices(
+        &mut self,
+        deps: Deps,
+        asset: &AssetInfoRaw,
+        collateral: &AssetInfoRaw,
+        check_expire: bool,
+    ) -> StdResult<(Decimal, (Decimal, Decimal, bool))> {
+        let asset_info: AssetInfo = asset.to_normal(deps.api)?;
+        let collateral_info: AssetInfo = collateral.to_normal(deps.api)?;
+        let asset_end_price = self.end_price(deps.storage, asset)?;
+        let asset_basket = self.basket(deps.storage, asset)?;
+        let asset_dual_price_feed = self.dual_price_feed(deps.storage, asset)?;
+        let collateral_end_price = self.end_price(deps.storage, collateral)?;
+
+        let mut assets: Vec<AssetInfo> = vec![];
+        if self.cached_asset_price(asset, check_expire).is_none()
+            && asset_end_price.is_none()
+            && asset_basket.is_none()
+            && asset_dual_price_feed.is_none()
+            && asset_info.to_string() != self.config.base_denom
+        {
+            assets.push(asset_info.clone());
+        }
+        if self
+            .cached_collateral_info(collateral, check_expire)
+            .is_none()
+            && collateral_end_price.is_none()
+            && collateral_info.to_string() != self.config.base_denom
+            && !assets.iter().any(|info| info.equal(&collateral_info))
+        {
+            assets.push(collateral_info.clone());
+        }
+
+        if !assets.is_empty() {
+            let collateral_oracle: Addr = deps.api.addr_humanize(&self.config.collateral_oracle)?;
+            let prices = query_prices(
+                &deps.querier,
+                collateral_oracle,
+                assets.clone(),
+                check_expire,
+            )?;
+            for (info, price) in assets.iter().zip(prices) {
+                if info.equal(&asset_info) {
+                    self.asset_prices
+                        .push((asset.clone(), check_expire, price.rate));
+                }
+                if info.equal(&collateral_info) {
+                    self.collateral_infos.push((
+                        collateral.clone(),
+                        check_expire,
+                        (price.rate, price.multiplier, price.is_revoked),
+                    ));
+                }
+            }
+        }
+
+        // everything left is cached or resolved without querying prices, a migrated
+        // mAsset collateral is valued at its end_price whatever its feed
+        Ok((
+            self.asset_price(deps, asset, check_expire)?,
+            self.collateral_info(deps, collateral, check_expire)?,
+        ))
+    }
+
+    fn cached_asset_price(&self, asset: &AssetInfoRaw, check_expire: bool) -> Option<Decimal> {
+        self.asset_prices
+            .iter()
+            .find(|(info, expire, _)| info.equal(asset) && *expire == check_expire)
+            .map(|(_, _, price)| *price)
+    }
+
+    fn cached_collateral_info(
+        &self,
+        collateral: &AssetInfoRaw,
+        check_expire: bool,
+    ) -> Option<(Decimal, Decimal, bool)> {
+        self.collateral_infos
+            .iter()
+            .find(|(info, expire, _)| info.equal(collateral) && *expire == check_expire)
+            .map(|(_, _, collateral_info)| *collateral_info)
+    }
+
+    // misses are cached as well, most collaterals are not registered assets;
+    // an asset config failing to load is an error, not a miss
+    fn load_asset_config(
+        &mut self,
+        storage: &dyn Storage,
+        asset_token: &CanonicalAddr,
+    ) -> StdResult<Option<AssetConfig>> {
+        if let Some((_, asset_config)) = self
+            .asset_configs
+            .iter()
+            .find(|(token, _)| token == asset_token)
+        {
+            return Ok(asset_config.clone());
+        }
+
+        let asset_config = may_read_asset_config(storage, asset_token)?;
+        self.asset_configs
+            .push((asset_token.clone(), asset_config.clone()));
+        Ok(asset_config)
+    }
+
+    // end_price of a migrated asset
+    fn end_price(
+        &mut self,
+        storage: &dyn Storage,
+        asset_info: &AssetInfoRaw,
+    ) -> StdResult<Option<Decimal>> {
+        Ok(match asset_info {
+            AssetInfoRaw::Token { contract_addr } => self
+                .load_asset_config(storage, contract_addr)?
+                .and_then(|asset_config| asset_config.end_price),
+            _ => None,
+        })
+    }
+
+    // oracle feed selected by the context for an asset with dual price feeds
+    fn dual_price_feed(
+        &mut self,
+        storage: &dyn Storage,
+        asset_info: &AssetInfoRaw,
+    ) -> StdResult<Option<String>> {
+        let price_feeds = match asset_info {
+            AssetInfoRaw::Token { contract_addr } => self
+                .load_asset_config(storage, contract_addr)?
+                .and_then(|asset_config| asset_config.price_feeds),
+            _ => None,
+        };
+        Ok(price_feeds.and_then(|price_feeds| match self.price_feed {
+            PriceFeed::Spot => None,
+            PriceFeed::Mint => Some(price_feeds.mint_feed),
+            PriceFeed::Liquidation => Some(price_feeds.liquidation_feed),
+        }))
+    }
+
+    // components of a basket asset
+    fn basket(
+        &mut self,
+        storage: &dyn Storage,
+        asset_info: &AssetInfoRaw,
+    ) -> StdResult<Option<Vec<BasketComponent>>> {
+        Ok(match asset_info {
+            AssetInfoRaw::Token { contract_addr } => self
+                .load_asset_config(storage, contract_addr)?
+                .and_then(|asset_config| asset_config.basket),
+            _ => None,
+        })
+    }
+}
@@ -1,29 +1,63 @@
 use crate::{
-    asserts::{assert_min_collateral_ratio, assert_protocol_fee},
-    migration::migrate_asset_configs,
+    alert::{query_triggered_alerts, set_alert},
+    asserts::{
+        assert_asset_fee_rate, assert_deadline, assert_fee_discount_tiers,
+        assert_flash_mint_fee_rate, assert_mel_fee_discount, assert_min_collateral_ratio,
+        assert_protocol_fee, assert_quote_denoms, assert_quote_denoms_sellable,
+        assert_referral_fee_rate,
+    },
+    basket::{basket_to_normal, update_asset_basket},
+    changelog::{admin_action_name, migrate_action_name, query_admin_actions, record_admin_action},
+    collateral_cap::{query_collateral_cap, update_collateral_cap},
+    flash_mint::{flash_mint, flash_mint_reply, FLASH_MINT_REPLY_ID},
+    freeze::{freeze_position, query_frozen_positions, unfreeze_position},
+    health_check::{cron_health_check, health_check},
+    ibc::{
+        ibc_receive_reply, ibc_transfer, query_ibc_outstanding, update_ibc_counterparty_ports,
+        IBC_RECEIVE_REPLY_ID,
+    },
+    integrator::{query_integrators, update_integrators},
+    journal::query_position_journal,
+    leverage::{
+        close_with_swap, close_with_swap_reply, deleverage, deleverage_reply, leverage,
+        leverage_reply, CLOSE_WITH_SWAP_REPLY_ID, DELEVERAGE_REPLY_ID, LEVERAGE_REPLY_ID,
+    },
+    migration::{migrate_batch, start_migration, start_totals_backfill},
+    position_nft::query_position_nft_info,
     positions::{
-        auction, burn, deposit, mint, open_position, query_next_position_idx, query_position,
-        query_positions, withdraw,
+        auction, burn, deposit, failed_message_reply, mint, open_position, prune,
+        query_archived_position, query_next_position_idx, query_position, query_position_fees,
+        query_position_summaries, query_positions, query_referred_positions,
+        query_underwater_positions, set_position_label, transfer_position, withdraw, MINT_REPLY_ID,
+        TRANSFER_REPLY_ID,
     },
+    price_feeds::update_asset_price_feeds,
+    quote_denoms::query_position_value,
+    session_key::{authorize_session_key, query_session_keys, revoke_session_key},
+    simulation::query_simulate_execute,
     state::{
-        read_asset_config, read_config, store_asset_config, store_config, store_position_idx,
-        AssetConfig, Config,
+        read_asset_config, read_asset_totals, read_asset_totals_complete, read_config,
+        read_fee_discount_tiers, store_asset_config, store_asset_totals_complete, store_config,
+        store_fee_discount_tiers, store_position_idx, AssetConfig, AssetTotals, Config,
     },
+    supply::{check_supply, query_supply_check, unpause_mint},
 };
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_binary, to_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut,
-    Env, MessageInfo, Response, StdError, StdResult, Uint128, WasmMsg,
+    attr, from_binary, to_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Reply, Response, StdError, StdResult, Uint128, WasmMsg,
 };
 use cw20::Cw20ReceiveMsg;
 use melange_protocol::mint::{
-    AssetConfigResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
-    QueryMsg,
+    AssetConfigResponse, AssetTotalsResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg,
+    FeeDiscountTier, FeeDiscountTiersResponse, InstantiateMsg, QueryLimits, QueryMsg,
 };
 use melange_protocol::{
+    asset::Asset,
+    attributes::{ACTION, MIGRATED_COUNT, MIGRATION_COMPLETE},
     collateral_oracle::{ExecuteMsg as CollateralOracleExecuteMsg, SourceType},
-    mint::MigrateMsg,
+    mint::{MigrateMsg, SudoMsg as MintSudoMsg},
 };
 
 use sei_cosmwasm::{
@@ -51,18 +85,69 @@ pub fn instantiate(
         staking: deps.api.addr_canonicalize(&msg.staking)?,
         melange_factory: deps.api.addr_canonicalize(&msg.melange_factory)?,
         lock: deps.api.addr_canonicalize(&msg.lock)?,
+        quote_denoms: assert_quote_denoms(&msg.base_denom, msg.quote_denoms.unwrap_or_default())?,
         base_denom: msg.base_denom,
         token_code_id: msg.token_code_id,
         protocol_fee_rate: assert_protocol_fee(msg.protocol_fee_rate)?,
+        referral_fee_rate: assert_referral_fee_rate(msg.referral_fee_rate)?,
+        closed_position_retention: msg.closed_position_retention,
+        query_limits: msg.query_limits.unwrap_or_default(),
+        position_nft: match msg.position_nft {
+            Some(position_nft) => Some(deps.api.addr_canonicalize(&position_nft)?),
+            None => None,
+        },
+        flash_mint_fee_rate: msg
+            .flash_mint_fee_rate
+            .map(assert_flash_mint_fee_rate)
+            .transpose()?,
+        dex_contract: match msg.dex_contract {
+            Some(dex_contract) => Some(deps.api.addr_canonicalize(&dex_contract)?),
+            None => None,
+        },
+        mel_token: match msg.mel_token {
+            Some(mel_token) => Some(deps.api.addr_canonicalize(&mel_token)?),
+            None => None,
+        },
+        mel_fee_discount: assert_mel_fee_discount(msg.mel_fee_discount)?,
+        guardian: match msg.guardian {
+            Some(guardian) => Some(deps.api.addr_canonicalize(&guardian)?),
+            None => None,
+        },
+        cron: match msg.cron {
+            Some(cron) => Some(deps.api.addr_canonicalize(&cron)?),
+            None => None,
+        },
     };
+    config.query_limits.assert_valid()?;
+    assert_quote_denoms_sellable(&config)?;
 
     store_config(deps.storage, &config)?;
     store_position_idx(deps.storage, Uint128::from(1u128))?;
+    store_asset_totals_complete(deps.storage, true)?;
     Ok(Response::default())
 }
 
+/// Dispatches the message; owner, guardian and governance actions are appended to the
+/// changelog once they succeeded
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    let admin_action = admin_action_name(&msg).map(|action| (action, msg.clone()));
+    let sender = info.sender.clone();
+    let response = execute_msg(deps.branch(), env.clone(), info, msg)?;
+
+    if let Some((action, msg)) = admin_action {
+        let actor = deps.api.addr_canonicalize(sender.as_str())?;
+        record_admin_action(deps.storage, &env, actor, action, &msg)?;
+    }
+    Ok(response)
+}
+
+fn execute_msg(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::UpdateConfig {
@@ -75,6 +160,23 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             token_code_id,
             protocol_fee_rate,
             staking,
+            referral_fee_rate,
+            closed_position_retention,
+            query_limits,
+            position_nft,
+            remove_position_nft,
+            flash_mint_fee_rate,
+            disable_flash_mint,
+            dex_contract,
+            remove_dex_contract,
+            mel_token,
+            remove_mel_token,
+            mel_fee_discount,
+            guardian,
+            remove_guardian,
+            cron,
+            remove_cron,
+            quote_denoms,
         } => update_config(
             deps,
             info,
@@ -87,10 +189,29 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             token_code_id,
             protocol_fee_rate,
             staking,
+            referral_fee_rate,
+            closed_position_retention,
+            query_limits,
+            position_nft,
+            remove_position_nft,
+            flash_mint_fee_rate,
+            disable_flash_mint,
+            dex_contract,
+            remove_dex_contract,
+            mel_token,
+            remove_mel_token,
+            mel_fee_discount,
+            guardian,
+            remove_guardian,
+            cron,
+            remove_cron,
+            quote_denoms,
         ),
         ExecuteMsg::UpdateAsset {
             asset_token,
             min_collateral_ratio,
+            mint_fee_rate,
+            burn_fee_rate,
         } => {
             let asset_addr = deps.api.addr_validate(asset_token.as_str())?;
             update_asset(
@@ -98,8 +219,21 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 info,
                 asset_addr,
                 min_collateral_ratio,
+                mint_fee_rate.map(assert_asset_fee_rate).transpose()?,
+                burn_fee_rate.map(assert_asset_fee_rate).transpose()?,
             )
         }
+        ExecuteMsg::UpdateAssetBasket {
+            asset_token,
+            basket,
+        } => update_asset_basket(deps, info, asset_token, basket),
+        ExecuteMsg::UpdateCollateralCap { collateral, cap } => {
+            update_collateral_cap(deps, info, collateral, cap)
+        }
+        ExecuteMsg::UpdateAssetPriceFeeds {
+            asset_token,
+            price_feeds,
+        } => update_asset_price_feeds(deps, info, asset_token, price_feeds),
         ExecuteMsg::RegisterAsset {
             asset_token,
             min_collateral_ratio,
@@ -119,13 +253,25 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             let asset_addr = deps.api.addr_validate(asset_token.as_str())?;
             register_migration(deps, info, asset_addr, end_price)
         }
+        ExecuteMsg::UpdateFeeDiscountTiers { tiers } => {
+            update_fee_discount_tiers(deps, info, tiers)
+        }
         ExecuteMsg::OpenPosition {
             collateral,
             asset_info,
             collateral_ratio,
+            referrer,
+            deadline,
+            min_mint_amount,
+            label,
         } => {
+            assert_deadline(&env, deadline)?;
             // todo: Check the actual deposit happens
 
+            let referrer = match referrer {
+                Some(referrer) => Some(deps.api.addr_validate(referrer.as_str())?),
+                None => None,
+            };
             open_position(
                 deps,
                 env,
@@ -133,6 +279,9 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 collateral,
                 asset_info,
                 collateral_ratio,
+                referrer,
+                min_mint_amount,
+                label,
             )
         }
         ExecuteMsg::Deposit {
@@ -147,16 +296,145 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             // Check the actual deposit happens
             collateral.assert_sent_native_token_balance(&info)?;
 
-            deposit(deps, info.sender, position_idx, collateral)
+            deposit(deps, env, info.sender, position_idx, collateral)
         }
         ExecuteMsg::Withdraw {
             position_idx,
             collateral,
-        } => withdraw(deps, info.sender, position_idx, collateral),
+            deadline,
+        } => {
+            assert_deadline(&env, deadline)?;
+            withdraw(deps, env, info.sender, position_idx, collateral)
+        }
         ExecuteMsg::Mint {
             position_idx,
             asset,
-        } => mint(deps, env, info.sender, position_idx, asset),
+            deadline,
+        } => {
+            assert_deadline(&env, deadline)?;
+            mint(deps, env, info.sender, position_idx, asset)
+        }
+        ExecuteMsg::Prune { limit } => prune(deps, env, limit),
+        ExecuteMsg::ContinueMigration {} => continue_migration(deps),
+        ExecuteMsg::HealthCheck {} => cron_health_check(deps, env, info),
+        ExecuteMsg::TransferPosition {
+            position_idx,
+            recipient,
+        } => {
+            let recipient = deps.api.addr_validate(recipient.as_str())?;
+            transfer_position(deps, env, info, position_idx, recipient)
+        }
+        ExecuteMsg::CheckSupply { asset_token } => check_supply(deps, info, asset_token),
+        ExecuteMsg::UnpauseMint { asset_token } => unpause_mint(deps, info, asset_token),
+        ExecuteMsg::UpdateIntegrators {
+            enabled,
+            add,
+            remove,
+        } => update_integrators(deps, info, enabled, add, remove),
+        ExecuteMsg::SetAlert {
+            position_idx,
+            ratio,
+        } => set_alert(deps, info, position_idx, ratio),
+        ExecuteMsg::FreezePosition {
+            position_idx,
+            reason,
+        } => freeze_position(deps, env, info, position_idx, reason),
+        ExecuteMsg::UnfreezePosition { position_idx } => {
+            unfreeze_position(deps, info, position_idx)
+        }
+        ExecuteMsg::SetPositionLabel {
+            position_idx,
+            label,
+        } => set_position_label(deps, info, position_idx, label),
+        ExecuteMsg::FlashMint {
+            asset,
+            amount,
+            callback_msg,
+        } => {
+            let asset_addr = deps.api.addr_validate(asset.as_str())?;
+            flash_mint(deps, env, info, asset_addr, amount, callback_msg)
+        }
+        ExecuteMsg::Leverage {
+            collateral,
+            asset_info,
+            target_leverage,
+            max_spread,
+        } => {
+            // Check the actual deposit happens
+            collateral.assert_sent_native_token_balance(&info)?;
+
+            leverage(
+                deps,
+                env,
+                info.sender,
+                collateral,
+                asset_info,
+                target_leverage,
+                max_spread,
+            )
+        }
+        ExecuteMsg::Deleverage {
+            position_idx,
+            target_ratio,
+            max_spread,
+        } => deleverage(
+            deps,
+            env,
+            info.sender,
+            position_idx,
+            target_ratio,
+            max_spread,
+        ),
+        ExecuteMsg::CloseWithSwap {
+            position_idx,
+            max_spread,
+            max_collateral_sold,
+        } => close_with_swap(
+            deps,
+            env,
+            info.sender,
+            position_idx,
+            max_spread,
+            max_collateral_sold,
+        ),
+        ExecuteMsg::AuthorizeSessionKey {
+            position_idx,
+            key,
+            expires_at,
+            daily_deposit_cap,
+            daily_burn_cap,
+        } => {
+            let key = deps.api.addr_validate(key.as_str())?;
+            authorize_session_key(
+                deps,
+                env,
+                info,
+                position_idx,
+                key,
+                expires_at,
+                daily_deposit_cap,
+                daily_burn_cap,
+            )
+        }
+        ExecuteMsg::RevokeSessionKey { position_idx, key } => {
+            let key = deps.api.addr_validate(key.as_str())?;
+            revoke_session_key(deps, info, position_idx, key)
+        }
+        ExecuteMsg::IbcTransfer {
+            asset_token,
+            amount,
+            channel,
+            receiver,
+            timeout,
+        } => {
+            let asset_addr = deps.api.addr_validate(asset_token.as_str())?;
+            ibc_transfer(
+                deps, env, info, asset_addr, amount, channel, receiver, timeout,
+            )
+        }
+        ExecuteMsg::UpdateIbcCounterpartyPorts { add, remove } => {
+            update_ibc_counterparty_ports(deps, info, add, remove)
+        }
     }
 }
 
@@ -173,6 +451,23 @@ pub fn update_config(
     token_code_id: Option<u64>,
     protocol_fee_rate: Option<Decimal>,
     staking: Option<String>,
+    referral_fee_rate: Option<Decimal>,
+    closed_position_retention: Option<u64>,
+    query_limits: Option<QueryLimits>,
+    position_nft: Option<String>,
+    remove_position_nft: Option<bool>,
+    flash_mint_fee_rate: Option<Decimal>,
+    disable_flash_mint: Option<bool>,
+    dex_contract: Option<String>,
+    remove_dex_contract: Option<bool>,
+    mel_token: Option<String>,
+    remove_mel_token: Option<bool>,
+    mel_fee_discount: Option<Decimal>,
+    guardian: Option<String>,
+    remove_guardian: Option<bool>,
+    cron: Option<String>,
+    remove_cron: Option<bool>,
+    quote_denoms: Option<Vec<String>>,
 ) -> StdResult<Response> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -217,34 +512,217 @@ pub fn update_config(
         config.staking = deps.api.addr_canonicalize(&staking)?;
     }
 
+    if let Some(referral_fee_rate) = referral_fee_rate {
+        config.referral_fee_rate = assert_referral_fee_rate(referral_fee_rate)?;
+    }
+
+    if let Some(closed_position_retention) = closed_position_retention {
+        config.closed_position_retention = closed_position_retention;
+    }
+
+    if let Some(query_limits) = query_limits {
+        query_limits.assert_valid()?;
+        config.query_limits = query_limits;
+    }
+
+    if let Some(position_nft) = position_nft {
+        config.position_nft = Some(deps.api.addr_canonicalize(&position_nft)?);
+    }
+
+    if remove_position_nft == Some(true) {
+        config.position_nft = None;
+    }
+
+    if let Some(flash_mint_fee_rate) = flash_mint_fee_rate {
+        config.flash_mint_fee_rate = Some(assert_flash_mint_fee_rate(flash_mint_fee_rate)?);
+    }
+
+    if disable_flash_mint == Some(true) {
+        config.flash_mint_fee_rate = None;
+    }
+
+    if let Some(dex_contract) = dex_contract {
+        config.dex_contract = Some(deps.api.addr_canonicalize(&dex_contract)?);
+    }
+
+    if remove_dex_contract == Some(true) {
+        config.dex_contract = None;
+    }
+
+    if let Some(mel_token) = mel_token {
+        config.mel_token = Some(deps.api.addr_canonicalize(&mel_token)?);
+    }
+
+    if remove_mel_token == Some(true) {
+        config.mel_token = None;
+    }
+
+    if let Some(mel_fee_discount) = mel_fee_discount {
+        config.mel_fee_discount = assert_mel_fee_discount(mel_fee_discount)?;
+    }
+
+    if let Some(guardian) = guardian {
+        config.guardian = Some(deps.api.addr_canonicalize(&guardian)?);
+    }
+
+    if remove_guardian == Some(true) {
+        config.guardian = None;
+    }
+
+    if let Some(cron) = cron {
+        config.cron = Some(deps.api.addr_canonicalize(&cron)?);
+    }
+
+    if remove_cron == Some(true) {
+        config.cron = None;
+    }
+
+    if let Some(quote_denoms) = quote_denoms {
+        config.quote_denoms = assert_quote_denoms(&config.base_denom, quote_denoms)?;
+    }
+    assert_quote_denoms_sellable(&config)?;
+
     store_config(deps.storage, &config)?;
     Ok(Response::new().add_attribute("action", "update_config"))
 }
 
+pub fn update_fee_discount_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    tiers: Vec<FeeDiscountTier>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    assert_fee_discount_tiers(&tiers)?;
+    store_fee_discount_tiers(deps.storage, &tiers)?;
+    Ok(Response::new().add_attribute(ACTION, "update_fee_discount_tiers"))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::AssetConfig { asset_token } => to_binary(&query_asset_config(deps, asset_token)?),
+        QueryMsg::AssetTotals { asset_token } => to_binary(&query_asset_totals(deps, asset_token)?),
         QueryMsg::Position { position_idx } => to_binary(&query_position(deps, position_idx)?),
+        QueryMsg::TriggeredAlerts { page } => to_binary(&query_triggered_alerts(deps, page)?),
+        QueryMsg::CollateralCap { collateral } => {
+            to_binary(&query_collateral_cap(deps, collateral)?)
+        }
+        QueryMsg::SupplyCheck { asset_token } => to_binary(&query_supply_check(deps, asset_token)?),
+        QueryMsg::Integrators { page } => to_binary(&query_integrators(deps, page)?),
+        QueryMsg::SimulateExecute { sender, funds, msg } => {
+            to_binary(&query_simulate_execute(deps, env, sender, funds, *msg)?)
+        }
+        QueryMsg::AdminActions { page } => to_binary(&query_admin_actions(deps, page)?),
+        QueryMsg::FrozenPositions { page } => to_binary(&query_frozen_positions(deps, page)?),
+        QueryMsg::PositionFees { position_idx } => {
+            to_binary(&query_position_fees(deps, position_idx)?)
+        }
+        QueryMsg::PositionJournal { position_idx, page } => {
+            to_binary(&query_position_journal(deps, position_idx, page)?)
+        }
+        QueryMsg::PositionValue {
+            position_idx,
+            quote_denom,
+        } => to_binary(&query_position_value(deps, position_idx, quote_denom)?),
         QueryMsg::Positions {
             owner_addr,
             asset_token,
-            start_after,
-            limit,
-            order_by,
-        } => to_binary(&query_positions(
+            page,
+        } => to_binary(&query_positions(deps, owner_addr, asset_token, page)?),
+        QueryMsg::PositionSummaries {
+            owner_addr,
+            asset_token,
+            page,
+        } => to_binary(&query_position_summaries(
             deps,
             owner_addr,
             asset_token,
-            start_after,
-            limit,
-            order_by,
+            page,
         )?),
         QueryMsg::NextPositionIdx {} => to_binary(&query_next_position_idx(deps)?),
+        QueryMsg::PositionNftInfo { token_id } => {
+            to_binary(&query_position_nft_info(deps, token_id)?)
+        }
+        QueryMsg::UnderwaterPositions {
+            asset_token,
+            collateral,
+            page,
+        } => to_binary(&query_underwater_positions(
+            deps,
+            asset_token,
+            collateral,
+            page,
+        )?),
+        QueryMsg::ArchivedPosition { position_idx } => {
+            to_binary(&query_archived_position(deps, position_idx)?)
+        }
+        QueryMsg::ReferredPositions { referrer, page } => {
+            to_binary(&query_referred_positions(deps, referrer, page)?)
+        }
+        QueryMsg::FeeDiscountTiers {} => to_binary(&query_fee_discount_tiers(deps)?),
+        QueryMsg::SessionKeys { position_idx } => {
+            to_binary(&query_session_keys(deps, position_idx)?)
+        }
+        QueryMsg::IbcOutstanding {
+            channel,
+            asset_token,
+        } => to_binary(&query_ibc_outstanding(deps, channel, asset_token)?),
     }
 }
 
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let resp = ConfigResponse {
+        owner: deps.api.addr_humanize(&config.owner)?.to_string(),
+        oracle: deps.api.addr_humanize(&config.oracle)?.to_string(),
+        collector: deps.api.addr_humanize(&config.collector)?.to_string(),
+        collateral_oracle: deps
+            .api
+            .addr_humanize(&config.collateral_oracle)?
+            .to_string(),
+        staking: deps.api.addr_humanize(&config.staking)?.to_string(),
+        melange_factory: deps.api.addr_humanize(&config.melange_factory)?.to_string(),
+        lock: deps.api.addr_humanize(&config.lock)?.to_string(),
+        base_denom: config.base_denom,
+        quote_denoms: config.quote_denoms,
+        token_code_id: config.token_code_id,
+        protocol_fee_rate: config.protocol_fee_rate,
+        referral_fee_rate: config.referral_fee_rate,
+        closed_position_retention: config.closed_position_retention,
+        query_limits: config.query_limits,
+        position_nft: match config.position_nft {
+            Some(position_nft) => Some(deps.api.addr_humanize(&position_nft)?.to_string()),
+            None => None,
+        },
+        flash_mint_fee_rate: config.flash_mint_fee_rate,
+        dex_contract: match config.dex_contract {
+            Some(dex_contract) => Some(deps.api.addr_humanize(&dex_contract)?.to_string()),
+            None => None,
+        },
+        mel_token: match config.mel_token {
+            Some(mel_token) => Some(deps.api.addr_humanize(&mel_token)?.to_string()),
+            None => None,
+        },
+        mel_fee_discount: config.mel_fee_discount,
+        guardian: match config.guardian {
+            Some(guardian) => Some(deps.api.addr_humanize(&guardian)?.to_string()),
+            None => None,
+        },
+        cron: match config.cron {
+            Some(cron) => Some(deps.api.addr_humanize(&cron)?.to_string()),
+            None => None,
+        },
+    };
+
+    Ok(resp)
+}
+
 pub fn query_asset_config(deps: Deps, asset_token: String) -> StdResult<AssetConfigResponse> {
     let asset_config: AssetConfig = read_asset_config(
         deps.storage,
@@ -259,7 +737,101 @@ pub fn query_asset_config(deps: Deps, asset_token: String) -> StdResult<AssetCon
             .to_string(),
         min_collateral_ratio: asset_config.min_collateral_ratio,
         end_price: asset_config.end_price,
+        mint_fee_rate: asset_config.mint_fee_rate,
+        burn_fee_rate: asset_config.burn_fee_rate,
+        mint_paused: asset_config.mint_paused,
+        basket: match asset_config.basket {
+            Some(basket) => Some(basket_to_normal(deps.api, &basket)?),
+            None => None,
+        },
+        price_feeds: asset_config.price_feeds,
     };
 
     Ok(resp)
 }
+
+pub fn query_asset_totals(deps: Deps, asset_token: String) -> StdResult<AssetTotalsResponse> {
+    let totals: AssetTotals = read_asset_totals(
+        deps.storage,
+        &deps.api.addr_canonicalize(asset_token.as_str())?,
+    )?;
+
+    Ok(AssetTotalsResponse {
+        asset_token,
+        total_minted: totals.total_minted,
+        total_collateral: totals
+            .total_collateral
+            .iter()
+            .map(|collateral| collateral.to_normal(deps.api))
+            .collect::<StdResult<Vec<Asset>>>()?,
+        complete: read_asset_totals_complete(deps.storage)?,
+    })
+}
+
+pub fn query_fee_discount_tiers(deps: Deps) -> StdResult<FeeDiscountTiersResponse> {
+    Ok(FeeDiscountTiersResponse {
+        tiers: read_fee_discount_tiers(deps.storage)?,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, env: Env, msg: MintSudoMsg) -> StdResult<Response> {
+    match msg {
+        MintSudoMsg::HealthCheck {} => health_check(deps, env),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    // only the contract admin can migrate
+    let actor = match deps
+        .querier
+        .query_wasm_contract_info(env.contract.address.as_str())?
+        .admin
+    {
+        Some(admin) => deps.api.addr_canonicalize(&admin)?,
+        None => deps.api.addr_canonicalize(env.contract.address.as_str())?,
+    };
+    record_admin_action(deps.storage, &env, actor, migrate_action_name(&msg), &msg)?;
+
+    match msg {
+        MigrateMsg::RewriteStorage { batch_size } => {
+            let (migrated_count, complete) = start_migration(deps.storage, batch_size)?;
+            Ok(Response::new().add_attributes(vec![
+                attr(ACTION, "migrate"),
+                attr(MIGRATED_COUNT, migrated_count.to_string()),
+                attr(MIGRATION_COMPLETE, complete.to_string()),
+            ]))
+        }
+        MigrateMsg::BackfillTotals { batch_size } => {
+            let (migrated_count, complete) = start_totals_backfill(deps.storage, batch_size)?;
+            Ok(Response::new().add_attributes(vec![
+                attr(ACTION, "backfill_totals"),
+                attr(MIGRATED_COUNT, migrated_count.to_string()),
+                attr(MIGRATION_COMPLETE, complete.to_string()),
+            ]))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        FLASH_MINT_REPLY_ID => flash_mint_reply(deps, env),
+        LEVERAGE_REPLY_ID => leverage_reply(deps, env),
+        DELEVERAGE_REPLY_ID => deleverage_reply(deps, env),
+        CLOSE_WITH_SWAP_REPLY_ID => close_with_swap_reply(deps, env),
+        MINT_REPLY_ID | TRANSFER_REPLY_ID => failed_message_reply(msg),
+        IBC_RECEIVE_REPLY_ID => ibc_receive_reply(deps, msg),
+        _ => Err(StdError::generic_err("unknown reply id")),
+    }
+}
+
+pub fn continue_migration(deps: DepsMut) -> StdResult<Response> {
+    let (migrated_count, complete) = migrate_batch(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "continue_migration"),
+        attr(MIGRATED_COUNT, migrated_count.to_string()),
+        attr(MIGRATION_COMPLETE, complete.to_string()),
+    ]))
+}
@@ -0,0 +1,209 @@
+use cosmwasm_std::{
+    attr, to_binary, Addr, Binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, SubMsg, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use melange_protocol::{
+    asset::{query_token_balance, Asset, AssetInfo},
+    attributes::{asset_attributes, ACTION, BORROWER, FLASH_MINT_FEE},
+};
+
+use crate::{
+    asserts::assert_migrated_asset,
+    positions::asset_mint_msg,
+    state::{
+        read_asset_config, read_config, read_flash_mint, remove_flash_mint, store_flash_mint,
+        AssetConfig, Config, FlashMint,
+    },
+};
+
+pub const FLASH_MINT_REPLY_ID: u64 = 1;
+
+/// Mints `amount` of the asset to the sender and executes `callback_msg` on it.
+/// The sender has to transfer `amount` plus the flash mint fee back to this contract
+/// within the callback, which is checked once the callback returns
+pub fn flash_mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_token: Addr,
+    amount: Uint128,
+    callback_msg: Binary,
+) -> StdResult<Response> {
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Flash mint amount must be positive"));
+    }
+
+    // the callback must not be able to start a nested flash mint
+    if read_flash_mint(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Flash mint already in progress"));
+    }
+
+    let config: Config = read_config(deps.storage)?;
+    let fee_rate = config
+        .flash_mint_fee_rate
+        .ok_or_else(|| StdError::generic_err("Flash mints are disabled"))?;
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    assert_migrated_asset(&asset_config)?;
+    if asset_config.mint_paused {
+        return Err(StdError::generic_err("Minting the asset is paused"));
+    }
+
+    let fee = amount * fee_rate;
+    let balance_before =
+        query_token_balance(&deps.querier, asset_token.clone(), env.contract.address)?;
+    store_flash_mint(
+        deps.storage,
+        &FlashMint {
+            borrower: deps.api.addr_canonicalize(info.sender.as_str())?,
+            asset_token: asset_token_raw,
+            amount,
+            fee,
+            balance_before,
+        },
+    )?;
+
+    let asset = Asset {
+        info: AssetInfo::Token {
+            contract_addr: asset_token.to_string(),
+        },
+        amount,
+    };
+
+    Ok(Response::new()
+        .add_submessage(asset_mint_msg(&asset_token, &info.sender, amount)?)
+        .add_submessage(SubMsg::reply_on_success(
+            WasmMsg::Execute {
+                contract_addr: info.sender.to_string(),
+                msg: callback_msg,
+                funds: vec![],
+            },
+            FLASH_MINT_REPLY_ID,
+        ))
+        .add_attributes(
+            vec![
+                vec![attr(ACTION, "flash_mint"), attr(BORROWER, info.sender)],
+                asset_attributes(None, &asset),
+            ]
+            .concat(),
+        ))
+}
+
+/// Checks the repayment of the pending flash mint, burns the minted amount
+/// and sends the fee to the collector; fails the whole transaction if not repaid
+pub fn flash_mint_reply(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let flash_mint: FlashMint = read_flash_mint(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No flash mint in progress"))?;
+    remove_flash_mint(deps.storage);
+
+    let config: Config = read_config(deps.storage)?;
+    let asset_token = deps.api.addr_humanize(&flash_mint.asset_token)?;
+    let balance = query_token_balance(&deps.querier, asset_token.clone(), env.contract.address)?;
+    assert_repaid(&flash_mint, balance)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: asset_token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: flash_mint.amount,
+        })?,
+        funds: vec![],
+    })];
+    if !flash_mint.fee.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(&config.collector)?.to_string(),
+                amount: flash_mint.fee,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    let info = AssetInfo::Token {
+        contract_addr: asset_token.to_string(),
+    };
+    Ok(Response::new().add_messages(messages).add_attributes(
+        vec![
+            vec![
+                attr(ACTION, "flash_mint_repay"),
+                attr(BORROWER, deps.api.addr_humanize(&flash_mint.borrower)?),
+            ],
+            asset_attributes(
+                None,
+                &Asset {
+                    info: info.clone(),
+                    amount: flash_mint.amount,
+                },
+            ),
+            asset_attributes(
+                Some(FLASH_MINT_FEE),
+                &Asset {
+                    info,
+                    amount: flash_mint.fee,
+                },
+            ),
+        ]
+        .concat(),
+    ))
+}
+
+/// Checks that the balance grew by at least the minted amount plus the fee
+/// since the flash mint started
+fn assert_repaid(flash_mint: &FlashMint, balance: Uint128) -> StdResult<()> {
+    let repaid = balance.saturating_sub(flash_mint.balance_before);
+    if repaid < flash_mint.amount + flash_mint.fee {
+        return Err(StdError::generic_err(
+            "Flash mint must be repaid with the fee within the callback",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::CanonicalAddr;
+
+    fn flash_mint(amount: u128, fee: u128, balance_before: u128) -> FlashMint {
+        FlashMint {
+            borrower: CanonicalAddr::from(vec![1u8; 20]),
+            asset_token: CanonicalAddr::from(vec![2u8; 20]),
+            amount: Uint128::new(amount),
+            fee: Uint128::new(fee),
+            balance_before: Uint128::new(balance_before),
+        }
+    }
+
+    #[test]
+    fn repaid_with_fee() {
+        // 1000 minted with a fee of 3 on top of 50 already held
+        let pending = flash_mint(1000, 3, 50);
+        assert!(assert_repaid(&pending, Uint128::new(1053)).is_ok());
+
+        // repaying more than owed is accepted
+        assert!(assert_repaid(&pending, Uint128::new(2000)).is_ok());
+
+        // without a fee the minted amount is enough
+        assert!(assert_repaid(&flash_mint(1000, 0, 0), Uint128::new(1000)).is_ok());
+    }
+
+    #[test]
+    fn repaid_without_fee_fails() {
+        let pending = flash_mint(1000, 3, 50);
+        assert_eq!(
+            assert_repaid(&pending, Uint128::new(1050)).unwrap_err(),
+            StdError::generic_err("Flash mint must be repaid with the fee within the callback")
+        );
+        assert!(assert_repaid(&pending, Uint128::new(1052)).is_err());
+    }
+
+    #[test]
+    fn balance_below_start_fails() {
+        // the tokens held before the flash mint never count as repayment
+        let pending = flash_mint(1000, 3, 50);
+        assert!(assert_repaid(&pending, Uint128::new(40)).is_err());
+        assert!(assert_repaid(&pending, Uint128::zero()).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use cosmwasm_std::{attr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+use melange_protocol::{
+    attributes::{ACTION, POSITION_IDX},
+    common::{PageRequest, PageResponse},
+    mint::{FrozenPositionResponse, FrozenPositionsResponse},
+};
+
+use crate::{
+    asserts::assert_owner_or_guardian,
+    state::{
+        read_config, read_frozen_position, read_frozen_positions, read_position,
+        remove_frozen_position, store_frozen_position, Config, FrozenPosition,
+    },
+};
+
+const MAX_FREEZE_REASON_LENGTH: usize = 256;
+
+pub fn freeze_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position_idx: Uint128,
+    reason: String,
+) -> StdResult<Response> {
+    assert_owner_or_guardian(deps.as_ref(), &info)?;
+    // fails for unknown positions
+    read_position(deps.storage, position_idx)?;
+    if reason.is_empty() || reason.len() > MAX_FREEZE_REASON_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Freeze reason must be 1 to {} bytes",
+            MAX_FREEZE_REASON_LENGTH
+        )));
+    }
+    if read_frozen_position(deps.storage, position_idx)?.is_some() {
+        return Err(StdError::generic_err("Position is already frozen"));
+    }
+
+    store_frozen_position(
+        deps.storage,
+        position_idx,
+        &FrozenPosition {
+            reason: reason.clone(),
+            frozen_at: env.block.time.seconds(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "freeze_position"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr("reason", reason),
+        attr("frozen_by", info.sender),
+    ]))
+}
+
+pub fn unfreeze_position(
+    deps: DepsMut,
+    info: MessageInfo,
+    position_idx: Uint128,
+) -> StdResult<Response> {
+    assert_owner_or_guardian(deps.as_ref(), &info)?;
+    if read_frozen_position(deps.storage, position_idx)?.is_none() {
+        return Err(StdError::generic_err("Position is not frozen"));
+    }
+
+    remove_frozen_position(deps.storage, position_idx);
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "unfreeze_position"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr("unfrozen_by", info.sender),
+    ]))
+}
+
+pub fn query_frozen_positions(
+    deps: Deps,
+    page: Option<PageRequest>,
+) -> StdResult<FrozenPositionsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let page = page.unwrap_or_default();
+    let limit = config.query_limits.frozen_positions.resolve(page.limit);
+    let start_after = page.parse_cursor()?;
+    let positions: Vec<FrozenPositionResponse> =
+        read_frozen_positions(deps.storage, start_after, limit, page.order)?
+            .into_iter()
+            .map(|(position_idx, frozen_position)| FrozenPositionResponse {
+                position_idx,
+                reason: frozen_position.reason,
+                frozen_at: frozen_position.frozen_at,
+            })
+            .collect();
+    let page = PageResponse::new(
+        positions.last().map(|p| p.position_idx),
+        positions.len(),
+        limit,
+    );
+
+    Ok(FrozenPositionsResponse { positions, page })
+}
@@ -0,0 +1,67 @@
+use cosmwasm_std::{attr, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult};
+use melange_protocol::{
+    asset::{Asset, AssetInfo},
+    attributes::{asset_attributes, ACTION, ASSET, COLLATERAL, PRUNED_COUNT},
+};
+
+use crate::{
+    positions::prune_closed_positions,
+    state::{
+        read_asset_configs, read_asset_totals, read_asset_totals_complete, read_config,
+        read_position_idx, Config,
+    },
+};
+
+pub fn cron_health_check(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if config.cron != Some(sender_raw) {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    health_check(deps, env)
+}
+
+/// Prunes a page of closed positions and reports the protocol state in a `heartbeat` event,
+/// followed by one `asset_stats` event per registered asset. Every read is bounded by the
+/// page size or the number of assets, so it stays cheap enough to run each interval
+pub fn health_check(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+    let (messages, pruned_count) = prune_closed_positions(deps.branch(), &env, None)?;
+
+    let mut events = vec![Event::new("heartbeat").add_attributes(vec![
+        attr("time", env.block.time.seconds().to_string()),
+        attr(
+            "next_position_idx",
+            read_position_idx(deps.storage)?.to_string(),
+        ),
+        attr(PRUNED_COUNT, pruned_count.to_string()),
+        attr(
+            "totals_complete",
+            read_asset_totals_complete(deps.storage)?.to_string(),
+        ),
+    ])];
+    for asset_config in read_asset_configs(deps.storage)? {
+        let totals = read_asset_totals(deps.storage, &asset_config.token)?;
+        let minted = Asset {
+            info: AssetInfo::Token {
+                contract_addr: deps.api.addr_humanize(&asset_config.token)?.to_string(),
+            },
+            amount: totals.total_minted,
+        };
+
+        let mut event =
+            Event::new("asset_stats").add_attributes(asset_attributes(Some(ASSET), &minted));
+        for collateral in totals.total_collateral.iter() {
+            event = event.add_attributes(asset_attributes(
+                Some(COLLATERAL),
+                &collateral.to_normal(deps.api)?,
+            ));
+        }
+        events.push(event);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute(ACTION, "health_check")
+        .add_events(events))
+}
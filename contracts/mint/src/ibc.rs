@@ -0,0 +1,374 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    attr, from_binary, to_binary, Addr, Attribute, Binary, CosmosMsg, Deps, DepsMut, Env,
+    IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg,
+    IbcChannelOpenResponse, IbcMsg, IbcOrder, IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsg, SubMsgResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use melange_protocol::{
+    asset::{Asset, AssetInfo},
+    attributes::{asset_attributes, ACTION, CHANNEL, RECEIVER, SENDER},
+    ibc::{TransferAck, TransferPacket, IBC_APP_VERSION},
+    mint::IbcOutstandingResponse,
+};
+
+use crate::{
+    asserts::assert_migrated_asset,
+    state::{
+        decrease_ibc_outstanding, increase_ibc_outstanding, is_ibc_counterparty_port,
+        read_asset_config, read_config, read_ibc_channel, read_ibc_outstanding, read_ibc_receive,
+        remove_ibc_counterparty_port, store_ibc_channel, store_ibc_counterparty_port,
+        store_ibc_receive, AssetConfig, Config, IbcReceive,
+    },
+};
+
+pub const IBC_RECEIVE_REPLY_ID: u64 = 7;
+
+// packets not relayed within this many seconds are refunded
+const DEFAULT_IBC_TIMEOUT: u64 = 600;
+
+/// Adds or removes counterparty ports transfer channels can be opened with; channels
+/// already connected are unaffected
+pub fn update_ibc_counterparty_ports(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut attributes = vec![attr(ACTION, "update_ibc_counterparty_ports")];
+    for port_id in add.unwrap_or_default() {
+        store_ibc_counterparty_port(deps.storage, &port_id)?;
+        attributes.push(attr("added", port_id));
+    }
+    for port_id in remove.unwrap_or_default() {
+        remove_ibc_counterparty_port(deps.storage, &port_id);
+        attributes.push(attr("removed", port_id));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// Burns the asset from the sender, which has to grant this contract an allowance first,
+/// and sends it over the transfer channel to be minted as a voucher on the remote chain
+#[allow(clippy::too_many_arguments)]
+pub fn ibc_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset_token: Addr,
+    amount: Uint128,
+    channel: String,
+    receiver: String,
+    timeout: Option<u64>,
+) -> StdResult<Response> {
+    if amount.is_zero() {
+        return Err(StdError::generic_err("Transfer amount must be positive"));
+    }
+    read_ibc_channel(deps.storage, &channel)?;
+
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    assert_migrated_asset(&asset_config)?;
+
+    increase_ibc_outstanding(deps.storage, &channel, &asset_token_raw, amount)?;
+
+    let packet = TransferPacket {
+        asset_token: asset_token.to_string(),
+        amount,
+        sender: info.sender.to_string(),
+        receiver: receiver.clone(),
+    };
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::BurnFrom {
+                owner: info.sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }))
+        .add_message(IbcMsg::SendPacket {
+            channel_id: channel.clone(),
+            data: to_binary(&packet)?,
+            timeout: IbcTimeout::with_timestamp(
+                env.block
+                    .time
+                    .plus_seconds(timeout.unwrap_or(DEFAULT_IBC_TIMEOUT)),
+            ),
+        })
+        .add_attributes(
+            vec![
+                vec![
+                    attr(ACTION, "ibc_transfer"),
+                    attr(SENDER, info.sender),
+                    attr(RECEIVER, receiver),
+                    attr(CHANNEL, channel),
+                ],
+                asset_attributes(
+                    None,
+                    &Asset {
+                        info: AssetInfo::Token {
+                            contract_addr: asset_token.to_string(),
+                        },
+                        amount,
+                    },
+                ),
+            ]
+            .concat(),
+        ))
+}
+
+pub fn query_ibc_outstanding(
+    deps: Deps,
+    channel: String,
+    asset_token: String,
+) -> StdResult<IbcOutstandingResponse> {
+    let amount = read_ibc_outstanding(
+        deps.storage,
+        &channel,
+        &deps.api.addr_canonicalize(asset_token.as_str())?,
+    )?;
+
+    Ok(IbcOutstandingResponse {
+        channel,
+        asset_token,
+        amount,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> StdResult<IbcChannelOpenResponse> {
+    let channel = msg.channel();
+    if !is_ibc_counterparty_port(deps.storage, &channel.counterparty_endpoint.port_id) {
+        return Err(StdError::generic_err(
+            "Counterparty port is not allowed to open transfer channels",
+        ));
+    }
+    if channel.order != IbcOrder::Unordered {
+        return Err(StdError::generic_err("Transfer channels must be unordered"));
+    }
+    if channel.version != IBC_APP_VERSION {
+        return Err(StdError::generic_err(
+            "Unsupported transfer channel version",
+        ));
+    }
+    if let Some(counterparty_version) = msg.counterparty_version() {
+        if counterparty_version != IBC_APP_VERSION {
+            return Err(StdError::generic_err(
+                "Unsupported counterparty transfer channel version",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> StdResult<IbcBasicResponse> {
+    let channel = msg.channel();
+    store_ibc_channel(
+        deps.storage,
+        &channel.endpoint.channel_id,
+        &channel.counterparty_endpoint,
+    )?;
+
+    Ok(IbcBasicResponse::new().add_attributes(vec![
+        attr(ACTION, "ibc_channel_connect"),
+        attr(CHANNEL, channel.endpoint.channel_id.clone()),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_channel_close(
+    _deps: DepsMut,
+    _env: Env,
+    _msg: IbcChannelCloseMsg,
+) -> StdResult<IbcBasicResponse> {
+    // closing would strand the vouchers outstanding on the remote chain
+    Err(StdError::generic_err("Transfer channels cannot be closed"))
+}
+
+/// Mints the asset returning from the remote chain; failures, including a failed mint,
+/// are acknowledged as errors so the remote side refunds the vouchers
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> StdResult<IbcReceiveResponse> {
+    receive_transfer(deps, &msg.packet).or_else(|err| {
+        Ok(IbcReceiveResponse::new()
+            .set_ack(to_binary(&TransferAck::Error(err.to_string()))?)
+            .add_attributes(vec![
+                attr(ACTION, "ibc_receive"),
+                attr("error", err.to_string()),
+            ]))
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketAckMsg,
+) -> StdResult<IbcBasicResponse> {
+    let ack: TransferAck = from_binary(&msg.acknowledgement.data)?;
+    match ack {
+        TransferAck::Result(_) => Ok(IbcBasicResponse::new().add_attributes(vec![
+            attr(ACTION, "ibc_transfer_ack"),
+            attr(CHANNEL, msg.original_packet.src.channel_id),
+        ])),
+        TransferAck::Error(err) => {
+            refund_transfer(deps, &msg.original_packet, "ibc_transfer_error", Some(err))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> StdResult<IbcBasicResponse> {
+    refund_transfer(deps, &msg.packet, "ibc_transfer_timeout", None)
+}
+
+// every check runs before the outstanding amount is written, so a failed receive
+// acknowledged as an error leaves no state behind
+fn receive_transfer(deps: DepsMut, packet: &IbcPacket) -> StdResult<IbcReceiveResponse> {
+    let transfer: TransferPacket = from_binary(&packet.data)?;
+    let receiver = deps.api.addr_validate(&transfer.receiver)?;
+    let asset_token = deps.api.addr_validate(&transfer.asset_token)?;
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    read_asset_config(deps.storage, &asset_token_raw)?;
+
+    decrease_ibc_outstanding(
+        deps.storage,
+        &packet.dest.channel_id,
+        &asset_token_raw,
+        transfer.amount,
+    )?;
+    store_ibc_receive(
+        deps.storage,
+        &IbcReceive {
+            channel_id: packet.dest.channel_id.clone(),
+            asset_token: asset_token_raw,
+            amount: transfer.amount,
+        },
+    )?;
+
+    Ok(IbcReceiveResponse::new()
+        .set_ack(to_binary(&TransferAck::Result(Binary::from(vec![1])))?)
+        .add_submessage(SubMsg::reply_on_error(
+            mint_msg(&asset_token, &receiver, transfer.amount)?,
+            IBC_RECEIVE_REPLY_ID,
+        ))
+        .add_attributes(transfer_attributes(
+            "ibc_receive",
+            &transfer,
+            &packet.dest.channel_id,
+        )))
+}
+
+/// Restores the outstanding amount of a return whose mint failed, e.g. on a reached minter
+/// cap, and overwrites the acknowledgement with an error so the remote side refunds
+pub fn ibc_receive_reply(deps: DepsMut, msg: Reply) -> StdResult<Response> {
+    let err = match msg.result {
+        SubMsgResult::Err(err) => err,
+        SubMsgResult::Ok(_) => return Err(StdError::generic_err("unexpected reply")),
+    };
+
+    let ibc_receive = read_ibc_receive(deps.storage)?;
+    increase_ibc_outstanding(
+        deps.storage,
+        &ibc_receive.channel_id,
+        &ibc_receive.asset_token,
+        ibc_receive.amount,
+    )?;
+
+    Ok(Response::new()
+        .set_data(to_binary(&TransferAck::Error(err.clone()))?)
+        .add_attributes(vec![
+            attr(ACTION, "ibc_receive"),
+            attr(CHANNEL, ibc_receive.channel_id),
+            attr("error", err),
+        ]))
+}
+
+// mints the burned asset back to the sender of a transfer that did not go through
+fn refund_transfer(
+    deps: DepsMut,
+    packet: &IbcPacket,
+    action: &str,
+    error: Option<String>,
+) -> StdResult<IbcBasicResponse> {
+    let transfer: TransferPacket = from_binary(&packet.data)?;
+    let sender = deps.api.addr_validate(&transfer.sender)?;
+    let asset_token = deps.api.addr_validate(&transfer.asset_token)?;
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+
+    decrease_ibc_outstanding(
+        deps.storage,
+        &packet.src.channel_id,
+        &asset_token_raw,
+        transfer.amount,
+    )?;
+
+    let mut attributes = transfer_attributes(action, &transfer, &packet.src.channel_id);
+    if let Some(error) = error {
+        attributes.push(attr("error", error));
+    }
+
+    Ok(IbcBasicResponse::new()
+        .add_message(mint_msg(&asset_token, &sender, transfer.amount)?)
+        .add_attributes(attributes))
+}
+
+fn mint_msg(asset_token: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: asset_token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    }))
+}
+
+fn transfer_attributes(action: &str, transfer: &TransferPacket, channel: &str) -> Vec<Attribute> {
+    vec![
+        vec![
+            attr(ACTION, action),
+            attr(SENDER, transfer.sender.clone()),
+            attr(RECEIVER, transfer.receiver.clone()),
+            attr(CHANNEL, channel),
+        ],
+        asset_attributes(
+            None,
+            &Asset {
+                info: AssetInfo::Token {
+                    contract_addr: transfer.asset_token.clone(),
+                },
+                amount: transfer.amount,
+            },
+        ),
+    ]
+    .concat()
+}
@@ -0,0 +1,62 @@
+use cosmwasm_std::{attr, Deps, DepsMut, MessageInfo, Response, StdError, StdResult};
+use melange_protocol::{
+    attributes::ACTION,
+    common::{PageRequest, PageResponse},
+    mint::IntegratorsResponse,
+};
+
+use crate::state::{
+    read_config, read_integrator_allowlist_enabled, read_integrators, remove_integrator,
+    store_integrator, store_integrator_allowlist_enabled, Config,
+};
+
+pub fn update_integrators(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: Option<bool>,
+    add: Option<Vec<String>>,
+    remove: Option<Vec<String>>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let mut attributes = vec![attr(ACTION, "update_integrators")];
+    if let Some(enabled) = enabled {
+        store_integrator_allowlist_enabled(deps.storage, enabled)?;
+        attributes.push(attr("enabled", enabled.to_string()));
+    }
+    for integrator in add.unwrap_or_default() {
+        store_integrator(deps.storage, &deps.api.addr_canonicalize(&integrator)?)?;
+        attributes.push(attr("added", integrator));
+    }
+    for integrator in remove.unwrap_or_default() {
+        remove_integrator(deps.storage, &deps.api.addr_canonicalize(&integrator)?);
+        attributes.push(attr("removed", integrator));
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+pub fn query_integrators(deps: Deps, page: Option<PageRequest>) -> StdResult<IntegratorsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let page = page.unwrap_or_default();
+    let limit = config.query_limits.integrators.resolve(page.limit);
+    let start_after = match page.parse_cursor::<String>()? {
+        Some(cursor) => Some(deps.api.addr_canonicalize(&cursor)?),
+        None => None,
+    };
+
+    let integrators = read_integrators(deps.storage, start_after, limit, page.order)?
+        .iter()
+        .map(|integrator| Ok(deps.api.addr_humanize(integrator)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+    let page = PageResponse::new(integrators.last(), integrators.len(), limit);
+
+    Ok(IntegratorsResponse {
+        enabled: read_integrator_allowlist_enabled(deps.storage)?,
+        integrators,
+        page,
+    })
+}
@@ -0,0 +1,91 @@
+use cosmwasm_std::{Api, Deps, Env, StdResult, Uint128};
+use melange_protocol::{
+    asset::{Asset, AssetRaw},
+    common::{PageRequest, PageResponse},
+    mint::{JournalEntryResponse, PositionJournalResponse},
+};
+
+use crate::state::{
+    read_config, read_journal_entries, read_journal_header, Config, JournalEntry, Position,
+};
+
+/// Journal entry of `action` leaving the position as `position`, moving nothing;
+/// handlers fill in the amounts, prices and fees the action used
+pub fn journal_entry(env: &Env, action: &str, position: &Position) -> JournalEntry {
+    JournalEntry {
+        height: env.block.height,
+        time: env.block.time.seconds(),
+        action: action.to_string(),
+        owner: position.owner.clone(),
+        collateral_amount: Uint128::zero(),
+        asset_amount: Uint128::zero(),
+        asset_price: None,
+        collateral_price: None,
+        fees: vec![],
+        collateral_after: position.collateral.amount,
+        asset_after: position.asset.amount,
+    }
+}
+
+/// the fees an action charged as journaled, zero fees left out
+pub fn journal_fees(api: &dyn Api, fees: &[&Asset]) -> StdResult<Vec<AssetRaw>> {
+    fees.iter()
+        .filter(|fee| !fee.amount.is_zero())
+        .map(|fee| {
+            Ok(AssetRaw {
+                info: fee.info.to_raw(api)?,
+                amount: fee.amount,
+            })
+        })
+        .collect()
+}
+
+pub fn query_position_journal(
+    deps: Deps,
+    position_idx: Uint128,
+    page: Option<PageRequest>,
+) -> StdResult<PositionJournalResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let header = read_journal_header(deps.storage, position_idx)?;
+    let page = page.unwrap_or_default();
+    let limit = config.query_limits.position_journal.resolve(page.limit);
+
+    let entries = read_journal_entries(
+        deps.storage,
+        position_idx,
+        page.parse_cursor()?,
+        limit,
+        page.order,
+    )?
+    .into_iter()
+    .map(|(seq, entry)| {
+        Ok(JournalEntryResponse {
+            seq,
+            height: entry.height,
+            time: entry.time,
+            action: entry.action,
+            owner: deps.api.addr_humanize(&entry.owner)?.to_string(),
+            collateral_amount: entry.collateral_amount,
+            asset_amount: entry.asset_amount,
+            asset_price: entry.asset_price,
+            collateral_price: entry.collateral_price,
+            fees: entry
+                .fees
+                .iter()
+                .map(|fee| fee.to_normal(deps.api))
+                .collect::<StdResult<Vec<Asset>>>()?,
+            collateral_after: entry.collateral_after,
+            asset_after: entry.asset_after,
+        })
+    })
+    .collect::<StdResult<Vec<JournalEntryResponse>>>()?;
+    let page = PageResponse::new(entries.last().map(|entry| entry.seq), entries.len(), limit);
+
+    Ok(PositionJournalResponse {
+        position_idx,
+        collateral: header.collateral.to_normal(deps.api)?,
+        asset: header.asset.to_normal(deps.api)?,
+        entries,
+        page,
+    })
+}
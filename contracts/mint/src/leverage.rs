@@ -0,0 +1,888 @@
+use cosmwasm_std::{
+    attr, to_binary, Addr, Coin, CosmosMsg, Decimal, DepsMut, Env, Response, StdError, StdResult,
+    SubMsg, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use melange_protocol::{
+    asset::{Asset, AssetInfo, AssetInfoRaw, AssetRaw},
+    attributes::{
+        asset_attributes, ACTION, COLLATERAL, ITERATIONS, LEVERAGE, MINT_FEE, PROTOCOL_FEE, REFUND,
+    },
+    dex::{Cw20HookMsg as DexCw20HookMsg, ExecuteMsg as DexExecuteMsg},
+};
+
+use crate::{
+    asserts::{
+        assert_collateral_cap, assert_integrator, assert_migrated_asset, assert_not_frozen,
+        assert_revoked_collateral,
+    },
+    context::{Context, PriceFeed},
+    journal::{journal_entry, journal_fees},
+    math::{
+        decimal_division, decimal_multiplication, decimal_subtraction, mint_amount, reverse_decimal,
+    },
+    positions::{
+        accrue_referral_fee, asset_mint_msgs, load_protocol_fee_rate, position_attributes,
+        position_nft_mint_msg, record_protocol_fee,
+    },
+    querier::query_asset_balance,
+    quote_denoms::protocol_fee_msg,
+    state::{
+        append_journal_entry, create_position, read_closing_position, read_deleverage_loop,
+        read_leverage_loop, read_position, read_position_idx, remove_closing_position,
+        remove_deleverage_loop, remove_leverage_loop, store_closing_position,
+        store_deleverage_loop, store_leverage_loop, store_position, store_position_idx,
+        AssetConfig, ClosingPosition, DeleverageLoop, JournalEntry, LeverageLoop, Position,
+    },
+};
+
+pub const LEVERAGE_REPLY_ID: u64 = 2;
+pub const DELEVERAGE_REPLY_ID: u64 = 3;
+pub const CLOSE_WITH_SWAP_REPLY_ID: u64 = 4;
+
+// bounds the swap rounds of a single leverage or deleverage execution
+const MAX_LEVERAGE_ITERATIONS: u32 = 10;
+
+/// Opens a position with the native collateral and levers it up to `target_leverage` times
+/// the initial collateral by repeatedly minting the asset, selling it for more collateral
+/// on the dex and depositing the proceeds
+pub fn leverage(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    collateral: Asset,
+    asset_info: AssetInfo,
+    target_leverage: Decimal,
+    max_spread: Decimal,
+) -> StdResult<Response> {
+    if !collateral.is_native_token() {
+        return Err(StdError::generic_err(
+            "Leverage requires a native collateral",
+        ));
+    }
+    if collateral.amount.is_zero() {
+        return Err(StdError::generic_err("Wrong collateral"));
+    }
+    if target_leverage <= Decimal::one() {
+        return Err(StdError::generic_err(
+            "Target leverage must be greater than 1",
+        ));
+    }
+    if max_spread >= Decimal::one() {
+        return Err(StdError::generic_err("max_spread must be lower than 1"));
+    }
+    if read_leverage_loop(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Leverage already in progress"));
+    }
+    assert_integrator(deps.as_ref(), &sender)?;
+
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
+    if ctx.config.dex_contract.is_none() {
+        return Err(StdError::generic_err("No dex contract configured"));
+    }
+
+    let collateral_info_raw: AssetInfoRaw = collateral.info.to_raw(deps.api)?;
+    let asset_info_raw: AssetInfoRaw = asset_info.to_raw(deps.api)?;
+    let (asset_price, collateral_info) =
+        ctx.prices(deps.as_ref(), &asset_info_raw, &collateral_info_raw, true)?;
+    let (collateral_price, collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
+
+    let asset_token_raw = match asset_info_raw.clone() {
+        AssetInfoRaw::Token { contract_addr } => contract_addr,
+        _ => return Err(StdError::generic_err("Asset must be a token")),
+    };
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
+    assert_migrated_asset(&asset_config)?;
+
+    // with all swapped collateral deposited back, the loop converges to a
+    // collateral ratio of L / (L - 1); every round mints down to exactly that ratio
+    let loop_collateral_ratio = decimal_division(
+        target_leverage,
+        decimal_subtraction(target_leverage, Decimal::one()),
+    );
+    if loop_collateral_ratio
+        < decimal_multiplication(asset_config.min_collateral_ratio, collateral_multiplier)
+    {
+        return Err(StdError::generic_err(
+            "Target leverage exceeds the maximum allowed by the minimum collateral ratio",
+        ));
+    }
+
+    // the loop deposits up to the target collateral within this transaction
+    let target_collateral = collateral.amount * target_leverage;
+    assert_collateral_cap(deps.storage, &collateral_info_raw, target_collateral)?;
+
+    let position_idx = read_position_idx(deps.storage)?;
+    let position = Position {
+        idx: position_idx,
+        owner: deps.api.addr_canonicalize(sender.as_str())?,
+        collateral: AssetRaw {
+            amount: collateral.amount,
+            info: collateral_info_raw,
+        },
+        asset: AssetRaw {
+            amount: Uint128::zero(),
+            info: asset_info_raw,
+        },
+        referrer: None,
+        closed_at: None,
+        position_nft: ctx.config.position_nft.clone(),
+        label: None,
+    };
+    create_position(deps.storage, position_idx, &position)?;
+    store_position_idx(deps.storage, position_idx + Uint128::from(1u128))?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: collateral.amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            ..journal_entry(&env, "leverage", &position)
+        },
+    )?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if let Some(msg) = position_nft_mint_msg(deps.as_ref(), &position)? {
+        messages.push(msg);
+    }
+
+    let round = leverage_round(
+        deps,
+        &env,
+        &mut ctx,
+        LeverageLoop {
+            position_idx,
+            initial_collateral: collateral.amount,
+            target_collateral,
+            loop_collateral_ratio,
+            max_spread,
+            iterations: 0,
+            balance_before: Uint128::zero(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(round.messages)
+        .add_attributes(round.attributes))
+}
+
+/// Deposits the collateral bought by the last swap and starts the next round
+pub fn leverage_reply(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let mut leverage_loop: LeverageLoop = read_leverage_loop(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No leverage in progress"))?;
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
+
+    let mut position: Position = read_position(deps.storage, leverage_loop.position_idx)?;
+    let collateral_info = position.collateral.info.to_normal(deps.api)?;
+    let balance = query_asset_balance(
+        &deps.querier,
+        &collateral_info,
+        env.contract.address.clone(),
+    )?;
+    let bought_amount = balance.saturating_sub(leverage_loop.balance_before);
+    position.collateral.amount += bought_amount;
+    store_position(deps.storage, leverage_loop.position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: bought_amount,
+            ..journal_entry(&env, "leverage_deposit", &position)
+        },
+    )?;
+
+    leverage_loop.iterations += 1;
+    leverage_round(deps, &env, &mut ctx, leverage_loop)
+}
+
+// mints the asset down to the loop collateral ratio and sells it for collateral,
+// or ends the loop once the target is reached or the iterations are exhausted
+fn leverage_round(
+    mut deps: DepsMut,
+    env: &Env,
+    ctx: &mut Context,
+    mut leverage_loop: LeverageLoop,
+) -> StdResult<Response> {
+    let mut position: Position = read_position(deps.storage, leverage_loop.position_idx)?;
+    let (asset_price, collateral_info) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, _collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
+    let collateral_price_in_asset = decimal_division(collateral_price, asset_price);
+
+    // never mint more than the collateral still missing to the target is worth
+    let max_mint_amount = mint_amount(
+        position.collateral.amount,
+        collateral_price,
+        asset_price,
+        leverage_loop.loop_collateral_ratio,
+    )?;
+    let remaining_collateral = leverage_loop
+        .target_collateral
+        .saturating_sub(position.collateral.amount);
+    let mint_amount = std::cmp::min(
+        max_mint_amount.saturating_sub(position.asset.amount),
+        remaining_collateral * collateral_price_in_asset,
+    );
+
+    if mint_amount.is_zero() || leverage_loop.iterations >= MAX_LEVERAGE_ITERATIONS {
+        remove_leverage_loop(deps.storage);
+
+        let mut attributes = vec![attr(ACTION, "leverage")];
+        attributes.extend(position_attributes(
+            deps.as_ref(),
+            &position,
+            asset_price,
+            collateral_price,
+        )?);
+        attributes.push(attr(
+            LEVERAGE,
+            Decimal::from_ratio(position.collateral.amount, leverage_loop.initial_collateral)
+                .to_string(),
+        ));
+        attributes.push(attr(ITERATIONS, leverage_loop.iterations.to_string()));
+        return Ok(Response::new().add_attributes(attributes));
+    }
+
+    position.asset.amount += mint_amount;
+    store_position(deps.storage, leverage_loop.position_idx, &position)?;
+
+    let dex_contract = match &ctx.config.dex_contract {
+        Some(dex_contract) => deps.api.addr_humanize(dex_contract)?,
+        None => return Err(StdError::generic_err("No dex contract configured")),
+    };
+    let asset_token = position.asset.info.to_normal(deps.api)?;
+    let collateral_info = position.collateral.info.to_normal(deps.api)?;
+
+    // only what is left after the asset mint fee gets sold
+    let asset_config = ctx.position_asset_config(deps.storage, &position)?;
+    let (mint_messages, mint_fee) = asset_mint_msgs(
+        deps.as_ref(),
+        &ctx.config,
+        &asset_config,
+        &env.contract.address,
+        mint_amount,
+    )?;
+    let sell_amount = mint_amount.checked_sub(mint_fee.amount)?;
+
+    // max_spread is applied against the oracle price of the asset
+    let minimum_receive = sell_amount
+        * decimal_division(asset_price, collateral_price)
+        * decimal_subtraction(Decimal::one(), leverage_loop.max_spread);
+
+    leverage_loop.balance_before = query_asset_balance(
+        &deps.querier,
+        &collateral_info,
+        env.contract.address.clone(),
+    )?;
+    store_leverage_loop(deps.storage, &leverage_loop)?;
+
+    let mut mint_attributes = asset_attributes(
+        None,
+        &Asset {
+            info: asset_token.clone(),
+            amount: mint_amount,
+        },
+    );
+    if !mint_fee.amount.is_zero() {
+        record_protocol_fee(deps.branch(), leverage_loop.position_idx, &mint_fee)?;
+        mint_attributes.extend(asset_attributes(Some(MINT_FEE), &mint_fee));
+    }
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            asset_amount: mint_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            fees: journal_fees(deps.api, &[&mint_fee])?,
+            ..journal_entry(env, "leverage_round", &position)
+        },
+    )?;
+    let sold = Asset {
+        info: asset_token,
+        amount: sell_amount,
+    };
+    Ok(Response::new()
+        .add_submessages(mint_messages)
+        .add_submessage(SubMsg::reply_on_success(
+            swap_msg(&dex_contract, &sold, collateral_info, minimum_receive, None)?,
+            LEVERAGE_REPLY_ID,
+        ))
+        .add_attributes(
+            vec![
+                vec![attr(ACTION, "leverage_round")],
+                position_attributes(deps.as_ref(), &position, asset_price, collateral_price)?,
+                mint_attributes,
+            ]
+            .concat(),
+        ))
+}
+
+/// Unwinds the position towards `target_ratio` by repeatedly withdrawing spare collateral,
+/// buying the asset with it on the dex and burning the proceeds against the debt
+pub fn deleverage(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    position_idx: Uint128,
+    target_ratio: Decimal,
+    max_spread: Decimal,
+) -> StdResult<Response> {
+    if target_ratio <= Decimal::one() {
+        return Err(StdError::generic_err("Target ratio must be greater than 1"));
+    }
+    if max_spread >= Decimal::one() {
+        return Err(StdError::generic_err("max_spread must be lower than 1"));
+    }
+    if read_deleverage_loop(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Deleverage already in progress"));
+    }
+
+    // every round withdraws collateral down to the minimum collateral ratio like `withdraw`
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
+    if ctx.config.dex_contract.is_none() {
+        return Err(StdError::generic_err("No dex contract configured"));
+    }
+
+    let position: Position = read_position(deps.storage, position_idx)?;
+    if sender != deps.api.addr_humanize(&position.owner)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    assert_not_frozen(deps.storage, position_idx)?;
+
+    // delisted assets are burned at their end price through the regular burn instead
+    let asset_token_raw = match position.asset.info.clone() {
+        AssetInfoRaw::Token { contract_addr } => contract_addr,
+        _ => return Err(StdError::generic_err("Asset must be a token")),
+    };
+    assert_migrated_asset(&ctx.asset_config(deps.storage, &asset_token_raw)?)?;
+
+    deleverage_round(
+        deps,
+        &env,
+        &mut ctx,
+        DeleverageLoop {
+            position_idx,
+            target_ratio,
+            max_spread,
+            iterations: 0,
+            balance_before: Uint128::zero(),
+        },
+    )
+}
+
+/// Burns the asset bought by the last swap, charging the protocol fee like a regular burn,
+/// and starts the next round
+pub fn deleverage_reply(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+    let mut deleverage_loop: DeleverageLoop = read_deleverage_loop(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No deleverage in progress"))?;
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
+
+    let mut position: Position = read_position(deps.storage, deleverage_loop.position_idx)?;
+    let position_owner = deps.api.addr_humanize(&position.owner)?;
+    let asset_info = position.asset.info.to_normal(deps.api)?;
+    let collateral_info = position.collateral.info.to_normal(deps.api)?;
+    let balance = query_asset_balance(&deps.querier, &asset_info, env.contract.address.clone())?;
+    let bought_amount = balance.saturating_sub(deleverage_loop.balance_before);
+    let burn_amount = std::cmp::min(bought_amount, position.asset.amount);
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut attributes = vec![attr(ACTION, "deleverage_burn")];
+    if !burn_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: asset_info.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: burn_amount,
+            })?,
+            funds: vec![],
+        }));
+        attributes.extend(asset_attributes(
+            None,
+            &Asset {
+                info: asset_info.clone(),
+                amount: burn_amount,
+            },
+        ));
+    }
+
+    // the swap can return slightly more than the remaining debt
+    let refund_amount = bought_amount.checked_sub(burn_amount)?;
+    if !refund_amount.is_zero() {
+        let refund = Asset {
+            info: asset_info,
+            amount: refund_amount,
+        };
+        attributes.extend(asset_attributes(Some(REFUND), &refund));
+        messages.push(refund.into_msg(&deps.querier, position_owner.clone())?);
+    }
+
+    let (asset_price, collateral_price) = {
+        let (asset_price, collateral_info) = ctx.prices(
+            deps.as_ref(),
+            &position.asset.info,
+            &position.collateral.info,
+            true,
+        )?;
+        (asset_price, assert_revoked_collateral(collateral_info)?.0)
+    };
+    let asset_config = ctx.position_asset_config(deps.storage, &position)?;
+    let protocol_fee_rate = load_protocol_fee_rate(
+        deps.branch(),
+        &env,
+        &ctx.config,
+        &asset_config,
+        &position_owner,
+    )?;
+    let protocol_fee = Asset {
+        info: collateral_info,
+        amount: burn_amount * decimal_division(asset_price, collateral_price) * protocol_fee_rate,
+    };
+    if !protocol_fee.amount.is_zero() {
+        let (fee_msg, collected_fee) = protocol_fee_msg(
+            deps.as_ref(),
+            &ctx.config,
+            &protocol_fee,
+            collateral_price,
+            Some(deleverage_loop.max_spread),
+        )?;
+        messages.push(fee_msg);
+        record_protocol_fee(deps.branch(), deleverage_loop.position_idx, &protocol_fee)?;
+        accrue_referral_fee(
+            deps.as_ref(),
+            &ctx.config,
+            &position,
+            &collected_fee,
+            &mut messages,
+            &mut attributes,
+        )?;
+        position.collateral.amount = position
+            .collateral
+            .amount
+            .checked_sub(protocol_fee.amount)?;
+    }
+    attributes.extend(asset_attributes(Some(PROTOCOL_FEE), &protocol_fee));
+
+    position.asset.amount = position.asset.amount.checked_sub(burn_amount)?;
+    store_position(deps.storage, deleverage_loop.position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: protocol_fee.amount,
+            asset_amount: burn_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            fees: journal_fees(deps.api, &[&protocol_fee])?,
+            ..journal_entry(&env, "deleverage_burn", &position)
+        },
+    )?;
+
+    deleverage_loop.iterations += 1;
+    let round = deleverage_round(deps, &env, &mut ctx, deleverage_loop)?;
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(attributes)
+        .add_submessages(round.messages)
+        .add_attributes(round.attributes))
+}
+
+// withdraws the collateral needed to reach the target ratio, bounded by the spare collateral
+// above the minimum collateral ratio, and sells it for the asset,
+// or ends the loop once the target is reached or the iterations are exhausted
+fn deleverage_round(
+    deps: DepsMut,
+    env: &Env,
+    ctx: &mut Context,
+    mut deleverage_loop: DeleverageLoop,
+) -> StdResult<Response> {
+    let mut position: Position = read_position(deps.storage, deleverage_loop.position_idx)?;
+    let asset_token_raw = match position.asset.info.clone() {
+        AssetInfoRaw::Token { contract_addr } => contract_addr,
+        _ => return Err(StdError::generic_err("Asset must be a token")),
+    };
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
+    let (asset_price, collateral_info) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
+    let asset_price_in_collateral = decimal_division(asset_price, collateral_price);
+
+    // debt and collateral are both compared in collateral units
+    let debt_value = position.asset.amount * asset_price_in_collateral;
+    let target_collateral = debt_value * deleverage_loop.target_ratio;
+
+    // withdrawing w and repaying w of debt reaches the target at w = (t * D - C) / (t - 1)
+    let needed_amount = target_collateral.saturating_sub(position.collateral.amount)
+        * reverse_decimal(decimal_subtraction(
+            deleverage_loop.target_ratio,
+            Decimal::one(),
+        ));
+    let spare_amount = position
+        .collateral
+        .amount
+        .saturating_sub(debt_value * asset_config.min_collateral_ratio * collateral_multiplier);
+    let withdraw_amount = std::cmp::min(std::cmp::min(needed_amount, spare_amount), debt_value);
+
+    if withdraw_amount.is_zero() || deleverage_loop.iterations >= MAX_LEVERAGE_ITERATIONS {
+        if deleverage_loop.iterations == 0 {
+            return Err(StdError::generic_err(
+                "Position has no spare collateral to deleverage or is already at the target ratio",
+            ));
+        }
+        remove_deleverage_loop(deps.storage);
+
+        let mut attributes = vec![attr(ACTION, "deleverage")];
+        attributes.extend(position_attributes(
+            deps.as_ref(),
+            &position,
+            asset_price,
+            collateral_price,
+        )?);
+        attributes.push(attr(ITERATIONS, deleverage_loop.iterations.to_string()));
+        return Ok(Response::new().add_attributes(attributes));
+    }
+
+    position.collateral.amount = position.collateral.amount.checked_sub(withdraw_amount)?;
+    store_position(deps.storage, deleverage_loop.position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: withdraw_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            ..journal_entry(env, "deleverage_round", &position)
+        },
+    )?;
+
+    let dex_contract = match &ctx.config.dex_contract {
+        Some(dex_contract) => deps.api.addr_humanize(dex_contract)?,
+        None => return Err(StdError::generic_err("No dex contract configured")),
+    };
+    let asset_info = position.asset.info.to_normal(deps.api)?;
+    let withdrawn = Asset {
+        info: position.collateral.info.to_normal(deps.api)?,
+        amount: withdraw_amount,
+    };
+
+    // max_spread is applied against the oracle price of the asset
+    let minimum_receive = withdraw_amount
+        * decimal_division(collateral_price, asset_price)
+        * decimal_subtraction(Decimal::one(), deleverage_loop.max_spread);
+
+    deleverage_loop.balance_before =
+        query_asset_balance(&deps.querier, &asset_info, env.contract.address.clone())?;
+    store_deleverage_loop(deps.storage, &deleverage_loop)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            swap_msg(&dex_contract, &withdrawn, asset_info, minimum_receive, None)?,
+            DELEVERAGE_REPLY_ID,
+        ))
+        .add_attributes(
+            vec![
+                vec![attr(ACTION, "deleverage_round")],
+                position_attributes(deps.as_ref(), &position, asset_price, collateral_price)?,
+                asset_attributes(None, &withdrawn),
+            ]
+            .concat(),
+        ))
+}
+
+/// Repays the whole debt of the position by selling just enough collateral on the dex,
+/// the rest of the collateral is returned to the owner once the debt is burned
+pub fn close_with_swap(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    position_idx: Uint128,
+    max_spread: Decimal,
+    max_collateral_sold: Option<Uint128>,
+) -> StdResult<Response> {
+    if max_spread >= Decimal::one() {
+        return Err(StdError::generic_err("max_spread must be lower than 1"));
+    }
+    if read_closing_position(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("Close with swap already in progress"));
+    }
+
+    let mut ctx = Context::load(deps.storage)?;
+    let dex_contract = match &ctx.config.dex_contract {
+        Some(dex_contract) => deps.api.addr_humanize(dex_contract)?,
+        None => return Err(StdError::generic_err("No dex contract configured")),
+    };
+
+    let mut position: Position = read_position(deps.storage, position_idx)?;
+    if sender != deps.api.addr_humanize(&position.owner)? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    assert_not_frozen(deps.storage, position_idx)?;
+    if position.asset.amount.is_zero() {
+        return Err(StdError::generic_err("Position has no debt to repay"));
+    }
+
+    let asset_token_raw = match position.asset.info.clone() {
+        AssetInfoRaw::Token { contract_addr } => contract_addr,
+        _ => return Err(StdError::generic_err("Asset must be a token")),
+    };
+    assert_migrated_asset(&ctx.asset_config(deps.storage, &asset_token_raw)?)?;
+
+    let (asset_price, collateral_info) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, _collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
+
+    // sell enough collateral to buy the whole debt even at the worst accepted spread
+    let sell_amount = position.asset.amount
+        * decimal_division(asset_price, collateral_price)
+        * reverse_decimal(decimal_subtraction(Decimal::one(), max_spread));
+    if sell_amount > position.collateral.amount {
+        return Err(StdError::generic_err(
+            "Collateral is not enough to buy back the debt",
+        ));
+    }
+    if let Some(max_collateral_sold) = max_collateral_sold {
+        if sell_amount > max_collateral_sold {
+            return Err(StdError::generic_err(format!(
+                "Closing would sell {} collateral, more than the maximum of {}",
+                sell_amount, max_collateral_sold
+            )));
+        }
+    }
+
+    position.collateral.amount = position.collateral.amount.checked_sub(sell_amount)?;
+    store_position(deps.storage, position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: sell_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            ..journal_entry(&env, "close_with_swap_sell", &position)
+        },
+    )?;
+
+    let asset_info = position.asset.info.to_normal(deps.api)?;
+    let sold = Asset {
+        info: position.collateral.info.to_normal(deps.api)?,
+        amount: sell_amount,
+    };
+    store_closing_position(
+        deps.storage,
+        &ClosingPosition {
+            position_idx,
+            max_spread,
+            balance_before: query_asset_balance(
+                &deps.querier,
+                &asset_info,
+                env.contract.address.clone(),
+            )?,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            swap_msg(
+                &dex_contract,
+                &sold,
+                asset_info,
+                position.asset.amount,
+                None,
+            )?,
+            CLOSE_WITH_SWAP_REPLY_ID,
+        ))
+        .add_attributes(
+            vec![
+                vec![attr(ACTION, "close_with_swap_sell")],
+                position_attributes(deps.as_ref(), &position, asset_price, collateral_price)?,
+                asset_attributes(None, &sold),
+            ]
+            .concat(),
+        ))
+}
+
+/// Burns the bought debt, charges the protocol fee like a regular burn
+/// and returns the remaining collateral and any excess asset to the owner
+pub fn close_with_swap_reply(mut deps: DepsMut, env: Env) -> StdResult<Response> {
+    let closing_position: ClosingPosition = read_closing_position(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No close with swap in progress"))?;
+    remove_closing_position(deps.storage);
+    let mut ctx = Context::load(deps.storage)?;
+
+    let mut position: Position = read_position(deps.storage, closing_position.position_idx)?;
+    let position_owner = deps.api.addr_humanize(&position.owner)?;
+    let asset_info = position.asset.info.to_normal(deps.api)?;
+    let collateral_info = position.collateral.info.to_normal(deps.api)?;
+    let burn_amount = position.asset.amount;
+
+    // the swap enforces a minimum receive of the whole debt
+    let balance = query_asset_balance(&deps.querier, &asset_info, env.contract.address.clone())?;
+    let refund_amount = balance
+        .saturating_sub(closing_position.balance_before)
+        .checked_sub(burn_amount)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: asset_info.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn {
+            amount: burn_amount,
+        })?,
+        funds: vec![],
+    })];
+    let mut attributes = vec![attr(ACTION, "close_with_swap")];
+
+    let (asset_price, collateral_info_res) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, _collateral_multiplier) =
+        assert_revoked_collateral(collateral_info_res)?;
+    let asset_config = ctx.position_asset_config(deps.storage, &position)?;
+    let protocol_fee_rate = load_protocol_fee_rate(
+        deps.branch(),
+        &env,
+        &ctx.config,
+        &asset_config,
+        &position_owner,
+    )?;
+    let protocol_fee = Asset {
+        info: collateral_info.clone(),
+        amount: std::cmp::min(
+            burn_amount * decimal_division(asset_price, collateral_price) * protocol_fee_rate,
+            position.collateral.amount,
+        ),
+    };
+    if !protocol_fee.amount.is_zero() {
+        let (fee_msg, collected_fee) = protocol_fee_msg(
+            deps.as_ref(),
+            &ctx.config,
+            &protocol_fee,
+            collateral_price,
+            Some(closing_position.max_spread),
+        )?;
+        messages.push(fee_msg);
+        record_protocol_fee(deps.branch(), closing_position.position_idx, &protocol_fee)?;
+        accrue_referral_fee(
+            deps.as_ref(),
+            &ctx.config,
+            &position,
+            &collected_fee,
+            &mut messages,
+            &mut attributes,
+        )?;
+    }
+
+    let returned_collateral = Asset {
+        info: collateral_info,
+        amount: position
+            .collateral
+            .amount
+            .checked_sub(protocol_fee.amount)?,
+    };
+    if !returned_collateral.amount.is_zero() {
+        messages.push(
+            returned_collateral
+                .clone()
+                .into_msg(&deps.querier, position_owner.clone())?,
+        );
+    }
+    if !refund_amount.is_zero() {
+        let refund = Asset {
+            info: asset_info.clone(),
+            amount: refund_amount,
+        };
+        attributes.extend(asset_attributes(Some(REFUND), &refund));
+        messages.push(refund.into_msg(&deps.querier, position_owner)?);
+    }
+
+    // closed positions are kept until they get pruned
+    position.asset.amount = Uint128::zero();
+    position.collateral.amount = Uint128::zero();
+    position.closed_at = Some(env.block.time.seconds());
+    store_position(deps.storage, closing_position.position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: protocol_fee.amount + returned_collateral.amount,
+            asset_amount: burn_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            fees: journal_fees(deps.api, &[&protocol_fee])?,
+            ..journal_entry(&env, "close_with_swap", &position)
+        },
+    )?;
+
+    attributes.extend(position_attributes(
+        deps.as_ref(),
+        &position,
+        asset_price,
+        collateral_price,
+    )?);
+    attributes.extend(asset_attributes(
+        None,
+        &Asset {
+            info: asset_info,
+            amount: burn_amount,
+        },
+    ));
+    attributes.extend(asset_attributes(Some(PROTOCOL_FEE), &protocol_fee));
+    attributes.extend(asset_attributes(Some(COLLATERAL), &returned_collateral));
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(attributes))
+}
+
+// sells the offer asset held by this contract on the dex, proceeds are sent to `to`,
+// back to this contract when not given
+pub fn swap_msg(
+    dex_contract: &Addr,
+    offer_asset: &Asset,
+    ask_asset_info: AssetInfo,
+    minimum_receive: Uint128,
+    to: Option<&Addr>,
+) -> StdResult<WasmMsg> {
+    Ok(match &offer_asset.info {
+        AssetInfo::Token { contract_addr } => WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: dex_contract.to_string(),
+                amount: offer_asset.amount,
+                msg: to_binary(&DexCw20HookMsg::Swap {
+                    ask_asset_info,
+                    minimum_receive: Some(minimum_receive),
+                    to: to.map(|to| to.to_string()),
+                })?,
+            })?,
+            funds: vec![],
+        },
+        AssetInfo::NativeToken { denom } => WasmMsg::Execute {
+            contract_addr: dex_contract.to_string(),
+            msg: to_binary(&DexExecuteMsg::Swap {
+                offer_asset: offer_asset.clone(),
+                ask_asset_info,
+                minimum_receive: Some(minimum_receive),
+                to: to.map(|to| to.to_string()),
+            })?,
+            funds: vec![Coin {
+                denom: denom.to_string(),
+                amount: offer_asset.amount,
+            }],
+        },
+    })
+}
@@ -1,6 +1,25 @@
 pub mod contract;
+mod context;
 mod state;
 mod positions;
 mod asserts;
 mod querier;
 mod math;
+mod migration;
+mod flash_mint;
+mod leverage;
+mod ibc;
+mod session_key;
+mod freeze;
+mod alert;
+mod basket;
+mod changelog;
+mod collateral_cap;
+mod health_check;
+mod integrator;
+mod position_nft;
+mod price_feeds;
+mod supply;
+mod simulation;
+mod quote_denoms;
+mod journal;
@@ -1,4 +1,19 @@
-use cosmwasm_std::{Decimal, Uint128};
+//! Fixed point helpers of the mint contract.
+//!
+//! Token amounts derived from prices follow one rounding policy: the protocol never loses
+//! to rounding. Amounts handed to the user round down, amounts required from the user
+//! round up:
+//!
+//! - `mint_amount`: asset minted against collateral rounds down, so 10 collateral at a
+//!   price ratio of 1 and a collateral ratio of 3 mints 3, not 3.33
+//! - `required_collateral`: collateral a debt requires under the minimum collateral ratio
+//!   rounds up, so a debt of 10 at an asset to collateral price ratio of 1/3 requires 5 at a
+//!   ratio of 1.5, but 6 rather than 5.33 at a ratio of 1.6
+//!
+//! The `decimal_*` helpers predate the policy and truncate at 9 decimals.
+
+use cosmwasm_std::{Decimal, Fraction, StdError, StdResult, Uint128, Uint256, Uint512};
+use std::convert::TryFrom;
 
 const DECIMAL_FRACTIONAL: Uint128 = Uint128::new(1_000_000_000u128);
 
@@ -31,3 +46,214 @@ pub fn decimal_min(a: Decimal, b: Decimal) -> Decimal {
         b
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Asset minted against `collateral_amount` at `collateral_ratio`,
+/// `collateral_amount * collateral_price / asset_price / collateral_ratio` rounded down once
+pub fn mint_amount(
+    collateral_amount: Uint128,
+    collateral_price: Decimal,
+    asset_price: Decimal,
+    collateral_ratio: Decimal,
+) -> StdResult<Uint128> {
+    mul_ratio(
+        collateral_amount,
+        &[collateral_price.numerator(), collateral_ratio.denominator()],
+        &[asset_price.numerator(), collateral_ratio.numerator()],
+        Rounding::Down,
+    )
+}
+
+/// Collateral a debt of `asset_amount` requires at `min_collateral_ratio` scaled by the
+/// `collateral_multiplier`, `asset_amount * asset_price / collateral_price *
+/// min_collateral_ratio * collateral_multiplier` rounded up once
+pub fn required_collateral(
+    asset_amount: Uint128,
+    asset_price: Decimal,
+    collateral_price: Decimal,
+    min_collateral_ratio: Decimal,
+    collateral_multiplier: Decimal,
+) -> StdResult<Uint128> {
+    mul_ratio(
+        asset_amount,
+        &[
+            asset_price.numerator(),
+            min_collateral_ratio.numerator(),
+            collateral_multiplier.numerator(),
+        ],
+        &[
+            collateral_price.numerator(),
+            min_collateral_ratio.denominator(),
+            collateral_multiplier.denominator(),
+        ],
+        Rounding::Up,
+    )
+}
+
+// amount * product(numerators) / product(denominators) over 512 bits, rounded once;
+// three 18 decimal factors would overflow 256 bits from amounts of about 1e23 on
+fn mul_ratio(
+    amount: Uint128,
+    numerators: &[Uint128],
+    denominators: &[Uint128],
+    rounding: Rounding,
+) -> StdResult<Uint128> {
+    let numerator = numerators
+        .iter()
+        .try_fold(Uint512::from(amount), |product, factor| {
+            product.checked_mul(Uint512::from(*factor))
+        })?;
+    let denominator = denominators
+        .iter()
+        .try_fold(Uint512::one(), |product, factor| {
+            product.checked_mul(Uint512::from(*factor))
+        })?;
+    if denominator.is_zero() {
+        return Err(StdError::generic_err(
+            "Cannot convert an amount at a zero price or ratio",
+        ));
+    }
+
+    let mut quotient = numerator / denominator;
+    if rounding == Rounding::Up && !(numerator % denominator).is_zero() {
+        quotient += Uint512::one();
+    }
+    Ok(Uint128::try_from(Uint256::try_from(quotient)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn mint_amount_exact_division() {
+        // 30 collateral worth 2 each at a ratio of 1.5 mints exactly 40 assets worth 1
+        let amount = mint_amount(Uint128::new(30), dec("2"), dec("1"), dec("1.5")).unwrap();
+        assert_eq!(amount, Uint128::new(40));
+    }
+
+    #[test]
+    fn mint_amount_rounds_down() {
+        // 3.33 rounds down to 3
+        let amount = mint_amount(Uint128::new(10), dec("1"), dec("1"), dec("3")).unwrap();
+        assert_eq!(amount, Uint128::new(3));
+
+        // 9.999999999 rounds down to 9, never up to 10
+        let amount =
+            mint_amount(Uint128::new(10), dec("1"), dec("1"), dec("1.0000000001")).unwrap();
+        assert_eq!(amount, Uint128::new(9));
+    }
+
+    #[test]
+    fn mint_amount_zero_price_or_ratio() {
+        assert!(mint_amount(Uint128::new(10), dec("1"), Decimal::zero(), dec("1.5")).is_err());
+        assert!(mint_amount(Uint128::new(10), dec("1"), dec("1"), Decimal::zero()).is_err());
+        // collateral worth nothing mints nothing
+        let amount = mint_amount(Uint128::new(10), Decimal::zero(), dec("1"), dec("1.5")).unwrap();
+        assert_eq!(amount, Uint128::zero());
+    }
+
+    #[test]
+    fn mint_amount_overflow() {
+        // intermediate products beyond 128 bits are fine while the result fits
+        let amount = mint_amount(Uint128::MAX, dec("2"), dec("2"), dec("1")).unwrap();
+        assert_eq!(amount, Uint128::MAX);
+        // a result beyond 128 bits is an error
+        let amount = mint_amount(Uint128::MAX, dec("2"), dec("1"), dec("1"));
+        assert!(amount.is_err());
+    }
+
+    #[test]
+    fn required_collateral_exact_division() {
+        // a debt of 10 at an asset to collateral price ratio of 1/3 and a ratio of 1.5
+        let amount =
+            required_collateral(Uint128::new(10), dec("1"), dec("3"), dec("1.5"), dec("1"))
+                .unwrap();
+        assert_eq!(amount, Uint128::new(5));
+    }
+
+    #[test]
+    fn required_collateral_rounds_up() {
+        // 5.33 rounds up to 6
+        let amount =
+            required_collateral(Uint128::new(10), dec("1"), dec("3"), dec("1.6"), dec("1"))
+                .unwrap();
+        assert_eq!(amount, Uint128::new(6));
+
+        // 10.000000001 rounds up to 11, never down to 10
+        let amount = required_collateral(
+            Uint128::new(10),
+            dec("1"),
+            dec("1"),
+            dec("1.0000000001"),
+            dec("1"),
+        )
+        .unwrap();
+        assert_eq!(amount, Uint128::new(11));
+    }
+
+    #[test]
+    fn required_collateral_rounds_multiplier_once() {
+        // 1.000000000000000001 * 0.5 truncates to 0.5 as a Decimal product, which would
+        // require exactly 50; as a separate factor the requirement rounds up to 51
+        let amount = required_collateral(
+            Uint128::new(100),
+            dec("1"),
+            dec("1"),
+            dec("1.000000000000000001"),
+            dec("0.5"),
+        )
+        .unwrap();
+        assert_eq!(amount, Uint128::new(51));
+    }
+
+    #[test]
+    fn required_collateral_zero_price_or_ratio() {
+        assert!(required_collateral(
+            Uint128::new(10),
+            dec("1"),
+            Decimal::zero(),
+            dec("1.5"),
+            dec("1")
+        )
+        .is_err());
+        // a worthless debt or no minimum ratio requires no collateral
+        let amount = required_collateral(
+            Uint128::new(10),
+            Decimal::zero(),
+            dec("1"),
+            dec("1.5"),
+            dec("1"),
+        )
+        .unwrap();
+        assert_eq!(amount, Uint128::zero());
+        let amount = required_collateral(
+            Uint128::new(10),
+            dec("1"),
+            dec("1"),
+            Decimal::zero(),
+            dec("1"),
+        )
+        .unwrap();
+        assert_eq!(amount, Uint128::zero());
+    }
+
+    #[test]
+    fn required_collateral_overflow() {
+        let amount =
+            required_collateral(Uint128::MAX, dec("1"), dec("1"), dec("1"), dec("1")).unwrap();
+        assert_eq!(amount, Uint128::MAX);
+        let amount = required_collateral(Uint128::MAX, dec("2"), dec("1"), dec("1.5"), dec("1"));
+        assert!(amount.is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use cosmwasm_std::{Order, StdError, StdResult, Storage};
+use cosmwasm_storage::Bucket;
+use cw_storage_plus::{Bound, Item};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    apply_position_totals, clear_asset_totals, positions, store_asset_totals_complete, Position,
+};
+
+static PREFIX_INDEX_BY_USER: &[u8] = b"by_user";
+static PREFIX_INDEX_BY_ASSET: &[u8] = b"by_asset";
+
+const MIGRATION_STATE: Item<MigrationState> = Item::new("migration_state");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationTask {
+    RewriteStorage,
+    BackfillTotals,
+}
+
+impl Default for MigrationTask {
+    fn default() -> Self {
+        MigrationTask::RewriteStorage
+    }
+}
+
+/// progress of a storage rewrite spread over several transactions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrationState {
+    pub batch_size: u32,
+    /// last position rewritten, none before the first batch
+    pub last_position_idx: Option<u128>,
+    #[serde(default)]
+    pub task: MigrationTask,
+}
+
+/// Starts rewriting the legacy storage layout and runs the first batch.
+/// Asset configs and primary position entries keep their byte layout under `Map`/`IndexedMap`,
+/// so only the position indexes are rebuilt and the legacy indexer buckets dropped
+pub fn start_migration(storage: &mut dyn Storage, batch_size: u32) -> StdResult<(usize, bool)> {
+    start_task(storage, MigrationTask::RewriteStorage, batch_size)
+}
+
+/// Starts recounting the asset totals from scratch and runs the first batch.
+/// Until the backfill passes a position, writes to it leave the totals alone
+pub fn start_totals_backfill(
+    storage: &mut dyn Storage,
+    batch_size: u32,
+) -> StdResult<(usize, bool)> {
+    clear_asset_totals(storage)?;
+    start_task(storage, MigrationTask::BackfillTotals, batch_size)
+}
+
+/// Last position counted by a running totals backfill, none when no backfill runs
+pub fn backfilled_until(storage: &dyn Storage) -> StdResult<Option<Option<u128>>> {
+    Ok(match MIGRATION_STATE.may_load(storage)? {
+        Some(state) if state.task == MigrationTask::BackfillTotals => Some(state.last_position_idx),
+        _ => None,
+    })
+}
+
+fn start_task(
+    storage: &mut dyn Storage,
+    task: MigrationTask,
+    batch_size: u32,
+) -> StdResult<(usize, bool)> {
+    if batch_size == 0 {
+        return Err(StdError::generic_err(
+            "Migration batch size must be positive",
+        ));
+    }
+    if let Some(state) = MIGRATION_STATE.may_load(storage)? {
+        if state.task != task {
+            return Err(StdError::generic_err(
+                "Another storage migration is still in progress",
+            ));
+        }
+    }
+
+    MIGRATION_STATE.save(
+        storage,
+        &MigrationState {
+            batch_size,
+            last_position_idx: None,
+            task,
+        },
+    )?;
+    migrate_batch(storage)
+}
+
+/// Rewrites the next batch of positions, returns the number of positions rewritten
+/// and whether the migration is complete
+pub fn migrate_batch(storage: &mut dyn Storage) -> StdResult<(usize, bool)> {
+    let mut state = MIGRATION_STATE
+        .may_load(storage)?
+        .ok_or_else(|| StdError::generic_err("No storage migration in progress"))?;
+
+    let batch: Vec<Position> = positions()
+        .range(
+            storage,
+            state.last_position_idx.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(state.batch_size as usize)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<Position>>>()?;
+
+    for position in batch.iter() {
+        match state.task {
+            MigrationTask::RewriteStorage => migrate_position(storage, position)?,
+            MigrationTask::BackfillTotals => apply_position_totals(storage, position, true)?,
+        }
+    }
+
+    let complete = batch.len() < state.batch_size as usize;
+    if complete {
+        MIGRATION_STATE.remove(storage);
+        if state.task == MigrationTask::BackfillTotals {
+            store_asset_totals_complete(storage, true)?;
+        }
+    } else {
+        state.last_position_idx = batch.last().map(|position| position.idx.u128());
+        MIGRATION_STATE.save(storage, &state)?;
+    }
+
+    Ok((batch.len(), complete))
+}
+
+// positions updated by users before the migration reaches them already carry their
+// new index entries; rewriting them again is idempotent
+fn migrate_position(storage: &mut dyn Storage, position: &Position) -> StdResult<()> {
+    let idx = position.idx.u128();
+
+    // no old data is given, so only the new index entries are written
+    positions().replace(storage, idx, Some(position), None)?;
+
+    let mut position_indexer_by_user: Bucket<bool> =
+        Bucket::multilevel(storage, &[PREFIX_INDEX_BY_USER, position.owner.as_slice()]);
+    position_indexer_by_user.remove(&idx.to_be_bytes());
+
+    let mut position_indexer_by_asset: Bucket<bool> = Bucket::multilevel(
+        storage,
+        &[PREFIX_INDEX_BY_ASSET, position.asset.info.as_bytes()],
+    );
+    position_indexer_by_asset.remove(&idx.to_be_bytes());
+
+    Ok(())
+}
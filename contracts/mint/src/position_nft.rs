@@ -0,0 +1,96 @@
+use cosmwasm_std::{Decimal, Deps, StdError, StdResult, Uint128};
+use melange_protocol::{
+    asset::Asset,
+    attributes::{ASSET, COLLATERAL, COLLATERAL_RATIO},
+    position_nft::{Metadata, NftInfoResponse, Trait},
+};
+use std::str::FromStr;
+
+use crate::{
+    context::{Context, PriceFeed},
+    math::{decimal_division, decimal_multiplication},
+    state::{read_position, Position},
+};
+
+/// Renders a tokenized position as cw721 metadata: collateral and debt, and while any asset
+/// is minted the collateral ratio and the asset price the position gets liquidated at
+pub fn query_position_nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResponse> {
+    let position_idx = Uint128::from_str(&token_id)
+        .map_err(|_| StdError::generic_err("Token id is not a position idx"))?;
+    let position: Position = read_position(deps.storage, position_idx)?;
+    if position.position_nft.is_none() {
+        return Err(StdError::generic_err("Position is not tokenized"));
+    }
+
+    let collateral: Asset = position.collateral.to_normal(deps.api)?;
+    let asset: Asset = position.asset.to_normal(deps.api)?;
+    let mut attributes = vec![
+        text_trait(COLLATERAL, collateral.info.to_string()),
+        number_trait("collateral_amount", collateral.amount.to_string()),
+        text_trait(ASSET, asset.info.to_string()),
+        number_trait("debt_amount", asset.amount.to_string()),
+    ];
+
+    if !position.asset.amount.is_zero() {
+        let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Liquidation);
+        let asset_config = ctx.position_asset_config(deps.storage, &position)?;
+        let (asset_price, (collateral_price, mut collateral_multiplier, _collateral_is_revoked)) =
+            ctx.prices(deps, &position.asset.info, &position.collateral.info, false)?;
+
+        // ignore multiplier for delisted assets
+        if asset_config.end_price.is_some() {
+            collateral_multiplier = Decimal::one();
+        }
+
+        let collateral_per_asset =
+            Decimal::from_ratio(position.collateral.amount, position.asset.amount);
+        if !asset_price.is_zero() {
+            let collateral_ratio = decimal_multiplication(
+                collateral_per_asset,
+                decimal_division(collateral_price, asset_price),
+            );
+            attributes.push(number_trait(COLLATERAL_RATIO, collateral_ratio.to_string()));
+        }
+
+        // asset price at which the collateral ratio reaches the minimum
+        let liquidation_price = decimal_division(
+            decimal_multiplication(collateral_per_asset, collateral_price),
+            decimal_multiplication(asset_config.min_collateral_ratio, collateral_multiplier),
+        );
+        attributes.push(number_trait(
+            "liquidation_price",
+            liquidation_price.to_string(),
+        ));
+    }
+
+    Ok(NftInfoResponse {
+        token_uri: None,
+        extension: Metadata {
+            name: Some(match position.label {
+                Some(label) => format!("Position #{}: {}", position_idx, label),
+                None => format!("Position #{}", position_idx),
+            }),
+            description: Some(format!(
+                "{} minted against {} collateral",
+                asset.info, collateral.info
+            )),
+            attributes: Some(attributes),
+        },
+    })
+}
+
+fn text_trait(trait_type: &str, value: String) -> Trait {
+    Trait {
+        display_type: None,
+        trait_type: trait_type.to_string(),
+        value,
+    }
+}
+
+fn number_trait(trait_type: &str, value: String) -> Trait {
+    Trait {
+        display_type: Some("number".to_string()),
+        trait_type: trait_type.to_string(),
+        value,
+    }
+}
@@ -1,63 +1,98 @@
 use cosmwasm_std::{
-    attr, to_binary, Addr, Attribute, CosmosMsg, Decimal, Deps, DepsMut, Env, Response, StdError,
-    StdResult, Uint128, WasmMsg,
+    attr, to_binary, Addr, Attribute, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Reply,
+    Response, StdError, StdResult, SubMsg, SubMsgResult, Uint128, WasmMsg,
 };
 
 use crate::{
     asserts::{
-        assert_asset, assert_collateral, assert_migrated_asset, assert_revoked_collateral,
+        assert_asset, assert_collateral, assert_collateral_cap, assert_integrator,
+        assert_migrated_asset, assert_not_frozen, assert_position_label, assert_revoked_collateral,
     },
+    context::{Context, PriceFeed},
+    journal::{journal_entry, journal_fees},
     math::{
-        decimal_division, decimal_min, decimal_multiplication, decimal_subtraction, reverse_decimal,
+        decimal_division, decimal_min, decimal_multiplication, decimal_subtraction, mint_amount,
+        required_collateral,
     },
-    querier::{load_asset_price, load_collateral_info},
+    querier::{query_staked_balance, select_fee_multiplier},
+    quote_denoms::protocol_fee_msg,
+    session_key::{spend_session_key, SessionAction},
     state::{
-        create_position, read_asset_config, read_config, read_position,
-        read_position_idx, read_positions, read_positions_with_asset_indexer,
-        read_positions_with_user_indexer, remove_position, store_position,
-        store_position_idx, AssetConfig, Config, Position,
-    }
+        add_position_fee, append_journal_entry, collateral_ratio_bucket_of, create_position,
+        read_archived_position, read_closed_positions, read_config, read_fee_discount_tiers,
+        read_fee_tier_cache, read_position, read_position_fees, read_position_idx, read_positions,
+        read_positions_with_asset_indexer, read_positions_with_collateral_ratio_indexer,
+        read_positions_with_referrer_indexer, read_positions_with_user_indexer, remove_position,
+        store_archived_position, store_fee_tier_cache, store_position, store_position_idx,
+        ArchivedPosition, AssetConfig, CollateralRatioScan, Config, FeeTierCache, JournalEntry,
+        Position, PositionScan,
+    },
 };
 
 use cw20::Cw20ExecuteMsg;
 use melange_protocol::{
-    common::OrderBy,
+    asset::{Asset, AssetInfo, AssetInfoRaw, AssetRaw},
+    attributes::{
+        asset_attributes, ACTION, ASSET, ASSET_PRICE, COLLATERAL, COLLATERAL_PRICE,
+        COLLATERAL_RATIO, MINT_FEE, OWNER, POSITION_IDX, PREVIOUS_OWNER, PROTOCOL_FEE,
+        PRUNED_COUNT, REFERRAL_FEE, REFERRER, REFUND,
+    },
+    collector::ExecuteMsg as CollectorExecuteMsg,
+    common::{PageRequest, PageResponse},
     lock::ExecuteMsg as LockExecuteMsg,
-    mint::{NextPositionIdxResponse, PositionResponse, PositionsResponse},
+    mint::{
+        ArchivedPositionResponse, NextPositionIdxResponse, PositionFeesResponse, PositionResponse,
+        PositionSummariesResponse, PositionSummary, PositionsResponse,
+    },
+    position_nft::ExecuteMsg as PositionNftExecuteMsg,
     staking::ExecuteMsg as StakingExecuteMsg,
-    asset::{Asset, AssetRaw, AssetInfo, AssetInfoRaw}
 };
 
+pub const MINT_REPLY_ID: u64 = 5;
+pub const TRANSFER_REPLY_ID: u64 = 6;
 
+#[allow(clippy::too_many_arguments)]
 pub fn open_position(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     collateral: Asset,
     asset_info: AssetInfo,
     collateral_ratio: Decimal,
+    referrer: Option<Addr>,
+    min_mint_amount: Option<Uint128>,
+    label: Option<String>,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
     if collateral.amount.is_zero() {
         return Err(StdError::generic_err("Wrong collateral"));
     }
+    assert_integrator(deps.as_ref(), &sender)?;
+    if let Some(label) = &label {
+        assert_position_label(label)?;
+    }
+    if referrer.as_ref() == Some(&sender) {
+        return Err(StdError::generic_err("Can not refer your own position"));
+    }
 
-    // assert the collateral is listed and has not been migrated/revoked
+    // load the asset price and the collateral info in one oracle round trip
     let collateral_info_raw: AssetInfoRaw = collateral.info.to_raw(deps.api)?;
-    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
-    let (collateral_price, collateral_multiplier) = assert_revoked_collateral(
-        load_collateral_info(deps.as_ref(), collateral_oracle, &collateral_info_raw, true)?,
-    )?;
+    let asset_info_raw: AssetInfoRaw = asset_info.to_raw(deps.api)?;
+    let (asset_price, collateral_info) =
+        ctx.prices(deps.as_ref(), &asset_info_raw, &collateral_info_raw, true)?;
+
+    // assert the collateral is listed and has not been migrated/revoked
+    let (collateral_price, collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
 
     // assert asset migrated
-    let asset_info_raw: AssetInfoRaw = asset_info.to_raw(deps.api)?;
     let asset_token_raw = match asset_info_raw.clone() {
         AssetInfoRaw::Token { contract_addr } => contract_addr,
         _ => panic!("DO NOT ENTER HERE"),
     };
 
-    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
     assert_migrated_asset(&asset_config)?;
+    assert_collateral_cap(deps.storage, &collateral_info_raw, collateral.amount)?;
 
     if collateral_ratio
         < decimal_multiplication(asset_config.min_collateral_ratio, collateral_multiplier)
@@ -67,75 +102,122 @@ pub fn open_position(
         ));
     }
 
-    let oracle: Addr = deps.api.addr_humanize(&config.oracle)?;
-    let asset_price: Decimal = load_asset_price(deps.as_ref(), oracle, &asset_info_raw, true)?;
-
-    let asset_price_in_collateral_asset = decimal_division(collateral_price, asset_price);
-
     // Convert collateral to mint amount
-    let mint_amount =
-        collateral.amount * asset_price_in_collateral_asset * reverse_decimal(collateral_ratio);
+    let mint_amount = mint_amount(
+        collateral.amount,
+        collateral_price,
+        asset_price,
+        collateral_ratio,
+    )?;
     if mint_amount.is_zero() {
         return Err(StdError::generic_err("collateral is too small"));
     }
+    let (mut messages, mint_fee) = asset_mint_msgs(
+        deps.as_ref(),
+        &ctx.config,
+        &asset_config,
+        &sender,
+        mint_amount,
+    )?;
+    // the oracle price may have moved since the sender quoted the mint amount
+    if let Some(min_mint_amount) = min_mint_amount {
+        let received_amount = mint_amount.checked_sub(mint_fee.amount)?;
+        if received_amount < min_mint_amount {
+            return Err(StdError::generic_err(format!(
+                "Mint amount {} is below the minimum of {}",
+                received_amount, min_mint_amount
+            )));
+        }
+    }
 
     let position_idx = read_position_idx(deps.storage)?;
     let asset_info_raw = asset_info.to_raw(deps.api)?;
 
-    create_position(
+    let position = Position {
+        idx: position_idx,
+        owner: deps.api.addr_canonicalize(sender.as_str())?,
+        collateral: AssetRaw {
+            amount: collateral.amount,
+            info: collateral_info_raw,
+        },
+        asset: AssetRaw {
+            amount: mint_amount,
+            info: asset_info_raw,
+        },
+        referrer: match &referrer {
+            Some(referrer) => Some(deps.api.addr_canonicalize(referrer.as_str())?),
+            None => None,
+        },
+        closed_at: None,
+        position_nft: ctx.config.position_nft.clone(),
+        label,
+    };
+    create_position(deps.storage, position_idx, &position)?;
+
+    if let Some(msg) = position_nft_mint_msg(deps.as_ref(), &position)? {
+        messages.push(SubMsg::new(msg));
+    }
+
+    let mut attributes = vec![attr(ACTION, "open_position")];
+    attributes.extend(position_attributes(
+        deps.as_ref(),
+        &position,
+        asset_price,
+        collateral_price,
+    )?);
+    attributes.extend(asset_attributes(Some(COLLATERAL), &collateral));
+    attributes.extend(asset_attributes(
+        Some(ASSET),
+        &Asset {
+            info: asset_info,
+            amount: mint_amount,
+        },
+    ));
+    if !mint_fee.amount.is_zero() {
+        record_protocol_fee(deps.branch(), position_idx, &mint_fee)?;
+        attributes.extend(asset_attributes(Some(MINT_FEE), &mint_fee));
+    }
+    if let Some(referrer) = referrer {
+        attributes.push(attr(REFERRER, referrer));
+    }
+    append_journal_entry(
         deps.storage,
-        position_idx,
-        &Position {
-            idx: position_idx,
-            owner: deps.api.addr_canonicalize(sender.as_str())?,
-            collateral: AssetRaw {
-                amount: collateral.amount,
-                info: collateral_info_raw,
-            },
-            asset: AssetRaw {
-                amount: mint_amount,
-                info: asset_info_raw,
-            },
+        &position,
+        &JournalEntry {
+            collateral_amount: collateral.amount,
+            asset_amount: mint_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            fees: journal_fees(deps.api, &[&mint_fee])?,
+            ..journal_entry(&env, "open_position", &position)
         },
     )?;
 
-    let asset_token = deps.api.addr_humanize(&asset_config.token)?.to_string();
-    let messages: Vec<CosmosMsg> = {
-        vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: asset_token,
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Mint {
-                recipient: sender.to_string(),
-                amount: mint_amount,
-            })?,
-        })]
-    };
-
     store_position_idx(deps.storage, position_idx + Uint128::from(1u128))?;
     Ok(Response::new()
-        .add_attributes(vec![
-            attr("action", "open_position"),
-            attr("position_idx", position_idx.to_string()),
-            attr(
-                "mint_amount",
-                mint_amount.to_string() + &asset_info.to_string(),
-            ),
-            attr("collateral_amount", collateral.to_string()),
-        ])
-        .add_messages(messages))
+        .add_attributes(attributes)
+        .add_submessages(messages))
 }
 
 pub fn deposit(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     sender: Addr,
     position_idx: Uint128,
     collateral: Asset,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    let mut ctx = Context::load(deps.storage)?;
     let mut position: Position = read_position(deps.storage, position_idx)?;
     let position_owner = deps.api.addr_humanize(&position.owner)?;
     if sender != position_owner {
-        return Err(StdError::generic_err("unauthorized"));
+        spend_session_key(
+            deps.branch(),
+            &env,
+            &position,
+            &sender,
+            SessionAction::Deposit,
+            collateral.amount,
+        )?;
     }
 
     // Check the given collateral has same asset info
@@ -143,46 +225,66 @@ pub fn deposit(
     // also Check the collateral amount is non-zero
     assert_collateral(deps.as_ref(), &position, &collateral)?;
 
-    // assert the collateral is listed and has not been migrated/revoked
-    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
-    assert_revoked_collateral(load_collateral_info(
+    // assert the collateral is listed and has not been migrated/revoked,
+    // the asset price is only needed for the resulting collateral ratio
+    let (asset_price, collateral_info) = ctx.prices(
         deps.as_ref(),
-        collateral_oracle,
+        &position.asset.info,
         &position.collateral.info,
         false,
-    )?)?;
+    )?;
+    let (collateral_price, _collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
 
     // assert asset migrated
     match position.asset.info.clone() {
         AssetInfoRaw::Token { contract_addr } => {
-            assert_migrated_asset(&read_asset_config(deps.storage, &contract_addr)?)?
+            assert_migrated_asset(&ctx.asset_config(deps.storage, &contract_addr)?)?
         }
         _ => panic!("DO NOT ENTER HERE"),
     };
+    assert_collateral_cap(deps.storage, &position.collateral.info, collateral.amount)?;
 
-    // Increase collateral amount
+    // Increase collateral amount, this reopens a closed position
     position.collateral.amount += collateral.amount;
+    position.closed_at = None;
     store_position(deps.storage, position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: collateral.amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            ..journal_entry(&env, "deposit", &position)
+        },
+    )?;
 
-    Ok(Response::new().add_attributes(vec![
-        attr("action", "deposit"),
-        attr("position_idx", position_idx.to_string()),
-        attr("deposit_amount", collateral.to_string()),
-    ]))
+    let mut attributes = vec![attr(ACTION, "deposit")];
+    attributes.extend(position_attributes(
+        deps.as_ref(),
+        &position,
+        asset_price,
+        collateral_price,
+    )?);
+    attributes.extend(asset_attributes(None, &collateral));
+
+    Ok(Response::new().add_attributes(attributes))
 }
 
 pub fn withdraw(
     deps: DepsMut,
+    env: Env,
     sender: Addr,
     position_idx: Uint128,
     collateral: Option<Asset>,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
     let mut position: Position = read_position(deps.storage, position_idx)?;
     let position_owner = deps.api.addr_humanize(&position.owner)?;
     if sender != position_owner {
         return Err(StdError::generic_err("unauthorized"));
     }
+    assert_not_frozen(deps.storage, position_idx)?;
 
     // if collateral is not provided, withraw all collateral
     let collateral: Asset = if let Some(collateral) = collateral {
@@ -207,19 +309,16 @@ pub fn withdraw(
         _ => panic!("DO NOT ENTER HERE"),
     };
 
-    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
-    let oracle: Addr = deps.api.addr_humanize(&config.oracle)?;
-    let asset_price: Decimal = load_asset_price(deps.as_ref(), oracle, &position.asset.info, true)?;
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
 
-    // Fetch collateral info from collateral oracle
-    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
-    let (collateral_price, mut collateral_multiplier, _collateral_is_revoked) =
-        load_collateral_info(
-            deps.as_ref(),
-            collateral_oracle,
-            &position.collateral.info,
-            true,
-        )?;
+    // Fetch asset price and collateral info in one oracle round trip
+    let (asset_price, collateral_info) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, mut collateral_multiplier, _collateral_is_revoked) = collateral_info;
 
     // ignore multiplier for delisted assets
     if asset_config.end_price.is_some() {
@@ -229,13 +328,14 @@ pub fn withdraw(
     // Compute new collateral amount
     let collateral_amount: Uint128 = position.collateral.amount.checked_sub(collateral.amount)?;
 
-    // Convert asset to collateral unit
-    let asset_value_in_collateral_asset: Uint128 =
-        position.asset.amount * decimal_division(asset_price, collateral_price);
-
     // Check minimum collateral ratio is satisfied
-    if asset_value_in_collateral_asset * asset_config.min_collateral_ratio * collateral_multiplier
-        > collateral_amount
+    if required_collateral(
+        position.asset.amount,
+        asset_price,
+        collateral_price,
+        asset_config.min_collateral_ratio,
+        collateral_multiplier,
+    )? > collateral_amount
     {
         return Err(StdError::generic_err(
             "Cannot withdraw collateral over than minimum collateral ratio",
@@ -246,34 +346,47 @@ pub fn withdraw(
 
     position.collateral.amount = collateral_amount;
 
+    // closed positions are kept until they get pruned
     if position.collateral.amount == Uint128::zero() && position.asset.amount == Uint128::zero() {
-
-        store_position(deps.storage, position_idx, &position)?;
+        position.closed_at = Some(env.block.time.seconds());
     }
+    store_position(deps.storage, position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            collateral_amount: collateral.amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            ..journal_entry(&env, "withdraw", &position)
+        },
+    )?;
+
+    let mut attributes = vec![attr(ACTION, "withdraw")];
+    attributes.extend(position_attributes(
+        deps.as_ref(),
+        &position,
+        asset_price,
+        collateral_price,
+    )?);
+    attributes.extend(asset_attributes(None, &collateral));
 
     Ok(Response::new()
-        .add_messages(
-            vec![
-                vec![collateral.clone().into_msg(&deps.querier, position_owner)?],
-                messages,
-            ]
-                .concat(),
-        )
-        .add_attributes(vec![
-            attr("action", "withdraw"),
-            attr("position_idx", position_idx.to_string()),
-            attr("withdraw_amount", collateral.to_string()),
-        ]))
+        .add_submessage(transfer_submsg(
+            collateral.into_msg(&deps.querier, position_owner)?,
+        ))
+        .add_messages(messages)
+        .add_attributes(attributes))
 }
 
 pub fn mint(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     position_idx: Uint128,
     asset: Asset,
 ) -> StdResult<Response> {
-    let config: Config = read_config(deps.storage)?;
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Mint);
     let mint_amount = asset.amount;
 
     let mut position: Position = read_position(deps.storage, position_idx)?;
@@ -281,6 +394,7 @@ pub fn mint(
     if sender != position_owner {
         return Err(StdError::generic_err("unauthorized"));
     }
+    assert_not_frozen(deps.storage, position_idx)?;
 
     assert_asset(deps.as_ref(), &position, &asset)?;
 
@@ -290,32 +404,29 @@ pub fn mint(
     };
 
     // assert the asset migrated
-    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
     assert_migrated_asset(&asset_config)?;
 
     // assert the collateral is listed and has not been migrated/revoked
-    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
-    let (collateral_price, collateral_multiplier) =
-        assert_revoked_collateral(load_collateral_info(
-            deps.as_ref(),
-            collateral_oracle,
-            &position.collateral.info,
-            true,
-        )?)?;
-
-    let oracle: Addr = deps.api.addr_humanize(&config.oracle)?;
-    let asset_price: Decimal = load_asset_price(deps.as_ref(), oracle, &position.asset.info, true)?;
+    let (asset_price, collateral_info) = ctx.prices(
+        deps.as_ref(),
+        &position.asset.info,
+        &position.collateral.info,
+        true,
+    )?;
+    let (collateral_price, collateral_multiplier) = assert_revoked_collateral(collateral_info)?;
 
     // Compute new asset amount
     let asset_amount: Uint128 = mint_amount + position.asset.amount;
 
-    // Convert asset to collateral unit
-    let asset_value_in_collateral_asset: Uint128 =
-        asset_amount * decimal_division(asset_price, collateral_price);
-
     // Check minimum collateral ratio is satisfied
-    if asset_value_in_collateral_asset * asset_config.min_collateral_ratio * collateral_multiplier
-        > position.collateral.amount
+    if required_collateral(
+        asset_amount,
+        asset_price,
+        collateral_price,
+        asset_config.min_collateral_ratio,
+        collateral_multiplier,
+    )? > position.collateral.amount
     {
         return Err(StdError::generic_err(
             "Cannot mint asset over than min collateral ratio",
@@ -325,49 +436,426 @@ pub fn mint(
     position.asset.amount += mint_amount;
     store_position(deps.storage, position_idx, &position)?;
 
-    let asset_token = deps.api.addr_humanize(&asset_config.token)?;
+    let (messages, mint_fee) = asset_mint_msgs(
+        deps.as_ref(),
+        &ctx.config,
+        &asset_config,
+        &position_owner,
+        mint_amount,
+    )?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &JournalEntry {
+            asset_amount: mint_amount,
+            asset_price: Some(asset_price),
+            collateral_price: Some(collateral_price),
+            fees: journal_fees(deps.api, &[&mint_fee])?,
+            ..journal_entry(&env, "mint", &position)
+        },
+    )?;
+
+    let mut attributes = vec![attr(ACTION, "mint")];
+    attributes.extend(position_attributes(
+        deps.as_ref(),
+        &position,
+        asset_price,
+        collateral_price,
+    )?);
+    attributes.extend(asset_attributes(None, &asset));
+    if !mint_fee.amount.is_zero() {
+        record_protocol_fee(deps.branch(), position_idx, &mint_fee)?;
+        attributes.extend(asset_attributes(Some(MINT_FEE), &mint_fee));
+    }
 
-    let messages: Vec<CosmosMsg> = {
-        vec![CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: deps.api.addr_humanize(&asset_config.token)?.to_string(),
+    Ok(Response::new()
+        .add_attributes(attributes)
+        .add_submessages(messages))
+}
+
+// cw20 mint of the asset, a failure is reported by `failed_message_reply`
+pub fn asset_mint_msg(asset_token: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<SubMsg> {
+    Ok(SubMsg::reply_on_error(
+        WasmMsg::Execute {
+            contract_addr: asset_token.to_string(),
             msg: to_binary(&Cw20ExecuteMsg::Mint {
-                amount: mint_amount,
-                recipient: position_owner.to_string(),
+                recipient: recipient.to_string(),
+                amount,
             })?,
             funds: vec![],
-        })]
+        },
+        MINT_REPLY_ID,
+    ))
+}
+
+// transfer of collateral or asset out of the contract, a failure is reported by
+// `failed_message_reply`
+pub fn transfer_submsg(msg: CosmosMsg) -> SubMsg {
+    SubMsg::reply_on_error(msg, TRANSFER_REPLY_ID)
+}
+
+/// Fails the transaction with the reason the token contract rejected the mint or transfer,
+/// e.g. a reached minter cap, so none of the accounting done before it is committed
+pub fn failed_message_reply(msg: Reply) -> StdResult<Response> {
+    let err = match msg.result {
+        SubMsgResult::Err(err) => err,
+        SubMsgResult::Ok(_) => return Err(StdError::generic_err("unexpected reply")),
     };
 
-    Ok(Response::new()
-        .add_attributes(vec![
-            attr("action", "mint"),
-            attr("position_idx", position_idx.to_string()),
-            attr("mint_amount", asset.to_string()),
-        ])
-        .add_messages(messages))
+    match msg.id {
+        MINT_REPLY_ID => Err(StdError::generic_err(format!("Asset mint failed: {}", err))),
+        _ => Err(StdError::generic_err(format!("Transfer failed: {}", err))),
+    }
+}
+
+// protocol fee rate of the asset after the MEL staker discount of the payer,
+// the resolved multiplier is cached per payer for the current block
+pub fn load_protocol_fee_rate(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    asset_config: &AssetConfig,
+    payer: &Addr,
+) -> StdResult<Decimal> {
+    let protocol_fee_rate = burn_fee_rate(config, asset_config);
+    let tiers = read_fee_discount_tiers(deps.storage)?;
+    if tiers.is_empty() {
+        return Ok(protocol_fee_rate);
+    }
+
+    let payer_raw = deps.api.addr_canonicalize(payer.as_str())?;
+    let fee_multiplier = match read_fee_tier_cache(deps.storage, &payer_raw)? {
+        Some(cache) if cache.height == env.block.height => cache.fee_multiplier,
+        _ => {
+            let staking = deps.api.addr_humanize(&config.staking)?;
+            let staked = query_staked_balance(&deps.querier, staking, payer.clone())?;
+            let fee_multiplier = select_fee_multiplier(&tiers, staked);
+            store_fee_tier_cache(
+                deps.storage,
+                &payer_raw,
+                &FeeTierCache {
+                    height: env.block.height,
+                    fee_multiplier,
+                },
+            )?;
+            fee_multiplier
+        }
+    };
+
+    Ok(decimal_multiplication(protocol_fee_rate, fee_multiplier))
+}
+
+// protocol fee rate the payer would be charged, like `load_protocol_fee_rate` but read-only
+fn query_protocol_fee_rate(
+    deps: Deps,
+    config: &Config,
+    asset_config: &AssetConfig,
+    payer: &Addr,
+) -> StdResult<Decimal> {
+    let protocol_fee_rate = burn_fee_rate(config, asset_config);
+    let tiers = read_fee_discount_tiers(deps.storage)?;
+    if tiers.is_empty() {
+        return Ok(protocol_fee_rate);
+    }
+
+    let staking = deps.api.addr_humanize(&config.staking)?;
+    let staked = query_staked_balance(&deps.querier, staking, payer.clone())?;
+    Ok(decimal_multiplication(
+        protocol_fee_rate,
+        select_fee_multiplier(&tiers, staked),
+    ))
+}
+
+// undiscounted protocol fee rate on burns of the asset
+fn burn_fee_rate(config: &Config, asset_config: &AssetConfig) -> Decimal {
+    asset_config
+        .burn_fee_rate
+        .unwrap_or(config.protocol_fee_rate)
+}
+
+// mints `mint_amount` of new debt, the asset mint fee to the collector and the rest to
+// `recipient`, returning the messages and the mint fee
+pub fn asset_mint_msgs(
+    deps: Deps,
+    config: &Config,
+    asset_config: &AssetConfig,
+    recipient: &Addr,
+    mint_amount: Uint128,
+) -> StdResult<(Vec<SubMsg>, Asset)> {
+    if asset_config.mint_paused {
+        return Err(StdError::generic_err("Minting the asset is paused"));
+    }
+
+    let asset_token = deps.api.addr_humanize(&asset_config.token)?;
+    let mint_fee = Asset {
+        info: AssetInfo::Token {
+            contract_addr: asset_token.to_string(),
+        },
+        amount: mint_amount * asset_config.mint_fee_rate,
+    };
+
+    let mut messages = vec![asset_mint_msg(
+        &asset_token,
+        recipient,
+        mint_amount.checked_sub(mint_fee.amount)?,
+    )?];
+    if !mint_fee.amount.is_zero() {
+        messages.push(asset_mint_msg(
+            &asset_token,
+            &deps.api.addr_humanize(&config.collector)?,
+            mint_fee.amount,
+        )?);
+    }
+
+    Ok((messages, mint_fee))
+}
+
+// books a protocol or mint fee charged to the position on its fee history
+pub fn record_protocol_fee(
+    deps: DepsMut,
+    position_idx: Uint128,
+    protocol_fee: &Asset,
+) -> StdResult<()> {
+    add_position_fee(
+        deps.storage,
+        position_idx,
+        AssetRaw {
+            info: protocol_fee.info.to_raw(deps.api)?,
+            amount: protocol_fee.amount,
+        },
+    )
+}
+
+// accrue the referrer share of the protocol fee on the collector, which credits a referrer
+// registered as a partner with its rebate on the whole fee instead
+pub fn accrue_referral_fee(
+    deps: Deps,
+    config: &Config,
+    position: &Position,
+    protocol_fee: &Asset,
+    messages: &mut Vec<CosmosMsg>,
+    attributes: &mut Vec<Attribute>,
+) -> StdResult<()> {
+    let referrer = match &position.referrer {
+        Some(referrer) => deps.api.addr_humanize(referrer)?,
+        None => return Ok(()),
+    };
+    let referral_fee = Asset {
+        info: protocol_fee.info.clone(),
+        amount: protocol_fee.amount * config.referral_fee_rate,
+    };
+
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&config.collector)?.to_string(),
+        msg: to_binary(&CollectorExecuteMsg::AccrueReferralFee {
+            referrer: referrer.to_string(),
+            fee: referral_fee.clone(),
+            protocol_fee: protocol_fee.clone(),
+        })?,
+        funds: vec![],
+    }));
+    attributes.push(attr(REFERRER, referrer));
+    if !referral_fee.amount.is_zero() {
+        attributes.extend(asset_attributes(Some(REFERRAL_FEE), &referral_fee));
+    }
+
+    Ok(())
+}
+
+// position_idx, owner, the prices the action used and, while any asset is minted,
+// the collateral ratio of the position
+pub fn position_attributes(
+    deps: Deps,
+    position: &Position,
+    asset_price: Decimal,
+    collateral_price: Decimal,
+) -> StdResult<Vec<Attribute>> {
+    let mut attributes = vec![
+        attr(POSITION_IDX, position.idx.to_string()),
+        attr(OWNER, deps.api.addr_humanize(&position.owner)?),
+    ];
+    if !asset_price.is_zero() {
+        attributes.push(attr(ASSET_PRICE, asset_price.to_string()));
+        attributes.push(attr(COLLATERAL_PRICE, collateral_price.to_string()));
+    }
+    if !position.asset.amount.is_zero() && !asset_price.is_zero() {
+        let collateral_ratio = decimal_multiplication(
+            Decimal::from_ratio(position.collateral.amount, position.asset.amount),
+            decimal_division(collateral_price, asset_price),
+        );
+        attributes.push(attr(COLLATERAL_RATIO, collateral_ratio.to_string()));
+    }
+
+    Ok(attributes)
 }
 
 pub fn query_position(deps: Deps, position_idx: Uint128) -> StdResult<PositionResponse> {
     let position: Position = read_position(deps.storage, position_idx)?;
-    let resp = PositionResponse {
+    to_position_response(deps, position)
+}
+
+pub fn query_position_fees(deps: Deps, position_idx: Uint128) -> StdResult<PositionFeesResponse> {
+    let position: Position = read_position(deps.storage, position_idx)?;
+    let mut ctx = Context::load(deps.storage)?;
+    let owner = deps.api.addr_humanize(&position.owner)?;
+    let asset_config = ctx.position_asset_config(deps.storage, &position)?;
+    let protocol_fee_rate = query_protocol_fee_rate(deps, &ctx.config, &asset_config, &owner)?;
+
+    let pending_fee_amount = if position.asset.amount.is_zero() {
+        Uint128::zero()
+    } else {
+        let (asset_price, (collateral_price, _, _)) =
+            ctx.prices(deps, &position.asset.info, &position.collateral.info, false)?;
+        position.asset.amount * decimal_division(asset_price, collateral_price) * protocol_fee_rate
+    };
+
+    Ok(PositionFeesResponse {
+        position_idx,
+        protocol_fee_rate,
+        pending_fee: Asset {
+            info: position.collateral.info.to_normal(deps.api)?,
+            amount: pending_fee_amount,
+        },
+        paid_fees: read_position_fees(deps.storage, position_idx)?
+            .iter()
+            .map(|fee| fee.to_normal(deps.api))
+            .collect::<StdResult<Vec<Asset>>>()?,
+    })
+}
+
+fn to_position_response(deps: Deps, position: Position) -> StdResult<PositionResponse> {
+    Ok(PositionResponse {
         idx: position.idx,
         owner: deps.api.addr_humanize(&position.owner)?.to_string(),
         collateral: position.collateral.to_normal(deps.api)?,
         asset: position.asset.to_normal(deps.api)?,
+        referrer: match position.referrer {
+            Some(referrer) => Some(deps.api.addr_humanize(&referrer)?.to_string()),
+            None => None,
+        },
+        tokenized: position.position_nft.is_some(),
+        label: position.label,
+    })
+}
+
+pub fn query_positions(
+    deps: Deps,
+    owner_addr: Option<String>,
+    asset_token: Option<String>,
+    page: Option<PageRequest>,
+) -> StdResult<PositionsResponse> {
+    let (positions, page) = read_filtered_positions(deps, owner_addr, asset_token, page)?;
+
+    let positions = positions
+        .into_iter()
+        .map(|position| to_position_response(deps, position))
+        .collect::<StdResult<Vec<PositionResponse>>>()?;
+
+    Ok(PositionsResponse { positions, page })
+}
+
+pub fn query_position_summaries(
+    deps: Deps,
+    owner_addr: Option<String>,
+    asset_token: Option<String>,
+    page: Option<PageRequest>,
+) -> StdResult<PositionSummariesResponse> {
+    let (positions, page) = read_filtered_positions(deps, owner_addr, asset_token, page)?;
+
+    let positions = positions
+        .into_iter()
+        .map(|position| {
+            Ok(PositionSummary {
+                idx: position.idx,
+                asset_token: position.asset.info.to_normal(deps.api)?.to_string(),
+                asset_amount: position.asset.amount,
+                collateral_amount: position.collateral.amount,
+            })
+        })
+        .collect::<StdResult<Vec<PositionSummary>>>()?;
+
+    Ok(PositionSummariesResponse { positions, page })
+}
+
+fn read_filtered_positions(
+    deps: Deps,
+    owner_addr: Option<String>,
+    asset_token: Option<String>,
+    page: Option<PageRequest>,
+) -> StdResult<(Vec<Position>, PageResponse)> {
+    let config: Config = read_config(deps.storage)?;
+    let page = page.unwrap_or_default();
+    let start_after = page.parse_cursor::<Uint128>()?;
+    let limit = config.query_limits.positions.resolve(page.limit);
+    let order_by = page.order;
+    let scan: PositionScan = if let Some(owner_addr) = owner_addr {
+        let owner_raw = deps.api.addr_canonicalize(owner_addr.as_str())?;
+        read_positions_with_user_indexer(deps.storage, &owner_raw, start_after, limit, order_by)?
+    } else if let Some(asset_token) = asset_token {
+        let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+        read_positions_with_asset_indexer(
+            deps.storage,
+            &asset_token_raw,
+            start_after,
+            limit,
+            order_by,
+        )?
+    } else {
+        read_positions(deps.storage, start_after, limit, order_by)?
     };
 
-    Ok(resp)
+    let page = scan_page(&scan, limit);
+    Ok((scan.positions, page))
+}
+
+/// a truncated scan continues after its last position even when the page is short
+fn scan_page(scan: &PositionScan, limit: usize) -> PageResponse {
+    let last = scan.positions.last().map(|p| p.idx);
+    if scan.truncated {
+        PageResponse::truncated(last)
+    } else {
+        PageResponse::new(last, scan.positions.len(), limit)
+    }
+}
+
+pub fn query_referred_positions(
+    deps: Deps,
+    referrer: String,
+    page: Option<PageRequest>,
+) -> StdResult<PositionsResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let page = page.unwrap_or_default();
+    let limit = config.query_limits.referred_positions.resolve(page.limit);
+    let referrer_raw = deps.api.addr_canonicalize(referrer.as_str())?;
+    let scan: PositionScan = read_positions_with_referrer_indexer(
+        deps.storage,
+        &referrer_raw,
+        page.parse_cursor()?,
+        limit,
+        page.order,
+    )?;
+    let page = scan_page(&scan, limit);
+
+    let positions = scan
+        .positions
+        .into_iter()
+        .map(|position| to_position_response(deps, position))
+        .collect::<StdResult<Vec<PositionResponse>>>()?;
+
+    Ok(PositionsResponse { positions, page })
 }
 
 pub fn burn(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     position_idx: Uint128,
     asset: Asset,
+    pay_fee_in_mel: bool,
+    max_spread: Option<Decimal>,
 ) -> StdResult<Response> {
     let burn_amount = asset.amount;
-    let config: Config = read_config(deps.storage)?;
+    let mut ctx = Context::load(deps.storage)?;
     let mut position: Position = read_position(deps.storage, position_idx)?;
     let position_owner = deps.api.addr_humanize(&position.owner)?;
     let collateral_info: AssetInfo = position.collateral.info.to_normal(deps.api)?;
@@ -381,7 +869,7 @@ pub fn burn(
         _ => panic!("DO NOT ENTER HERE"),
     };
 
-    let asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
     if position.asset.amount < burn_amount {
         return Err(StdError::generic_err(
             "Cannot burn asset more than you mint",
@@ -391,22 +879,26 @@ pub fn burn(
     let mut messages: Vec<CosmosMsg> = vec![];
     let mut attributes: Vec<Attribute> = vec![];
 
-    // fetch collateral info from collateral oracle
-    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
-    let (collateral_price, _collateral_multiplier, _collateral_is_revoked) = load_collateral_info(
-        deps.as_ref(),
-        collateral_oracle,
-        &position.collateral.info,
-        true,
-    )?;
+    // load the asset price and the collateral info in one oracle round trip,
+    // a delisted asset is priced at its end price
+    let (asset_price, (collateral_price, _collateral_multiplier, _collateral_is_revoked)) = ctx
+        .prices(
+            deps.as_ref(),
+            &position.asset.info,
+            &position.collateral.info,
+            true,
+        )?;
+
+    // protocol fee is paid by the sender, discounted by its MEL stake
+    let protocol_fee_rate =
+        load_protocol_fee_rate(deps.branch(), &env, &ctx.config, &asset_config, &sender)?;
 
     // If the collateral is default denom asset and the asset is deprecated,
     // anyone can execute burn the asset to any position without permission
     let mut close_position: bool = false;
+    let mut refund: Option<SubMsg> = None;
 
-    if let Some(end_price) = asset_config.end_price {
-        let asset_price: Decimal = end_price;
-
+    if asset_config.end_price.is_some() {
         let collateral_price_in_asset = decimal_division(asset_price, collateral_price);
 
         // Burn deprecated asset to receive collaterals back
@@ -427,73 +919,178 @@ pub fn burn(
             .checked_sub(refund_collateral.amount)
             .unwrap();
 
-        // due to rounding, include 1
-        if position.collateral.amount <= Uint128::from(1u128)
-            && position.asset.amount == Uint128::zero()
-        {
-            close_position = true;
-            remove_position(deps.storage, position_idx)?;
-        } else {
-            store_position(deps.storage, position_idx, &position)?;
-        }
-
         // Subtract protocol fee from refunded collateral
         let protocol_fee = Asset {
             info: collateral_info,
-            amount: burn_amount * collateral_price_in_asset * config.protocol_fee_rate,
+            amount: burn_amount * collateral_price_in_asset * protocol_fee_rate,
         };
 
         if !protocol_fee.amount.is_zero() {
-            messages.push(
-                protocol_fee
-                    .clone()
-                    .into_msg(&deps.querier, deps.api.addr_humanize(&config.collector)?)?,
-            );
+            let (fee_msg, collected_fee) = protocol_fee_msg(
+                deps.as_ref(),
+                &ctx.config,
+                &protocol_fee,
+                collateral_price,
+                max_spread,
+            )?;
+            messages.push(fee_msg);
+            record_protocol_fee(deps.branch(), position_idx, &protocol_fee)?;
+            accrue_referral_fee(
+                deps.as_ref(),
+                &ctx.config,
+                &position,
+                &collected_fee,
+                &mut messages,
+                &mut attributes,
+            )?;
             refund_collateral.amount = refund_collateral
                 .amount
                 .checked_sub(protocol_fee.amount)
                 .unwrap();
         }
-        attributes.push(attr("protocol_fee", protocol_fee.to_string()));
+        attributes.extend(asset_attributes(Some(PROTOCOL_FEE), &protocol_fee));
+        append_journal_entry(
+            deps.storage,
+            &position,
+            &JournalEntry {
+                // the fee is part of the collateral taken out of the position
+                collateral_amount: refund_collateral.amount + protocol_fee.amount,
+                asset_amount: burn_amount,
+                asset_price: Some(asset_price),
+                collateral_price: Some(collateral_price),
+                fees: journal_fees(deps.api, &[&protocol_fee])?,
+                ..journal_entry(&env, "burn", &position)
+            },
+        )?;
 
-        // Refund collateral msg
-        messages.push(refund_collateral.clone().into_msg(&deps.querier, sender)?);
+        // due to rounding, include 1; removing the position also drops the fee history
+        // written above, the journal is kept
+        if position.collateral.amount <= Uint128::from(1u128)
+            && position.asset.amount == Uint128::zero()
+        {
+            close_position = true;
+            remove_position(deps.storage, position_idx)?;
+            if let Some(msg) = position_nft_burn_msg(deps.as_ref(), &position)? {
+                messages.push(msg);
+            }
+        } else {
+            store_position(deps.storage, position_idx, &position)?;
+        }
 
-        attributes.push(attr(
-            "refund_collateral_amount",
-            refund_collateral.to_string(),
+        // Refund collateral msg
+        attributes.extend(asset_attributes(Some(REFUND), &refund_collateral));
+        refund = Some(transfer_submsg(
+            refund_collateral.into_msg(&deps.querier, sender)?,
         ));
     } else {
         if sender != position_owner {
-            return Err(StdError::generic_err("unauthorized"));
+            spend_session_key(
+                deps.branch(),
+                &env,
+                &position,
+                &sender,
+                SessionAction::Burn,
+                burn_amount,
+            )?;
         }
-        let oracle = deps.api.addr_humanize(&config.oracle)?;
-        let asset_price: Decimal =
-            load_asset_price(deps.as_ref(), oracle, &asset.info.to_raw(deps.api)?, true)?;
         let collateral_price_in_asset: Decimal = decimal_division(asset_price, collateral_price);
 
-        // Subtract the protocol fee from the position's collateral
-        let protocol_fee = Asset {
-            info: collateral_info,
-            amount: burn_amount * collateral_price_in_asset * config.protocol_fee_rate,
+        // the collector may be credited the fee in base_denom rather than as charged
+        let (protocol_fee, collected_fee) = if pay_fee_in_mel {
+            // The sender pays the discounted fee in MEL out of its allowance to this contract
+            let mel_token = ctx
+                .config
+                .mel_token
+                .clone()
+                .ok_or_else(|| StdError::generic_err("Paying fees in MEL is not enabled"))?;
+            let mel_price = ctx.asset_price(
+                deps.as_ref(),
+                &AssetInfoRaw::Token {
+                    contract_addr: mel_token.clone(),
+                },
+                true,
+            )?;
+            let mel_token = deps.api.addr_humanize(&mel_token)?;
+            let protocol_fee = Asset {
+                info: AssetInfo::Token {
+                    contract_addr: mel_token.to_string(),
+                },
+                amount: burn_amount
+                    * decimal_division(asset_price, mel_price)
+                    * protocol_fee_rate
+                    * decimal_subtraction(Decimal::one(), ctx.config.mel_fee_discount),
+            };
+
+            if !protocol_fee.amount.is_zero() {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: mel_token.to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                        owner: sender.to_string(),
+                        recipient: deps.api.addr_humanize(&ctx.config.collector)?.to_string(),
+                        amount: protocol_fee.amount,
+                    })?,
+                    funds: vec![],
+                }));
+            }
+            (protocol_fee.clone(), protocol_fee)
+        } else {
+            // Subtract the protocol fee from the position's collateral
+            let protocol_fee = Asset {
+                info: collateral_info,
+                amount: burn_amount * collateral_price_in_asset * protocol_fee_rate,
+            };
+
+            let mut collected_fee = protocol_fee.clone();
+            if !protocol_fee.amount.is_zero() {
+                let (fee_msg, fee) = protocol_fee_msg(
+                    deps.as_ref(),
+                    &ctx.config,
+                    &protocol_fee,
+                    collateral_price,
+                    max_spread,
+                )?;
+                messages.push(fee_msg);
+                collected_fee = fee;
+                position.collateral.amount = position
+                    .collateral
+                    .amount
+                    .checked_sub(protocol_fee.amount)?
+            }
+            (protocol_fee, collected_fee)
         };
 
         if !protocol_fee.amount.is_zero() {
-            messages.push(
-                protocol_fee
-                    .clone()
-                    .into_msg(&deps.querier, deps.api.addr_humanize(&config.collector)?)?,
-            );
-            position.collateral.amount = position
-                .collateral
-                .amount
-                .checked_sub(protocol_fee.amount)?
+            record_protocol_fee(deps.branch(), position_idx, &protocol_fee)?;
+            accrue_referral_fee(
+                deps.as_ref(),
+                &ctx.config,
+                &position,
+                &collected_fee,
+                &mut messages,
+                &mut attributes,
+            )?;
         }
-        attributes.push(attr("protocol_fee", protocol_fee.to_string()));
+        attributes.extend(asset_attributes(Some(PROTOCOL_FEE), &protocol_fee));
 
         // Update asset amount
         position.asset.amount = position.asset.amount.checked_sub(burn_amount).unwrap();
         store_position(deps.storage, position_idx, &position)?;
+        append_journal_entry(
+            deps.storage,
+            &position,
+            &JournalEntry {
+                collateral_amount: if pay_fee_in_mel {
+                    Uint128::zero()
+                } else {
+                    protocol_fee.amount
+                },
+                asset_amount: burn_amount,
+                asset_price: Some(asset_price),
+                collateral_price: Some(collateral_price),
+                fees: journal_fees(deps.api, &[&protocol_fee])?,
+                ..journal_entry(&env, "burn", &position)
+            },
+        )?;
     }
 
     let asset_token = deps.api.addr_humanize(&asset_config.token)?;
@@ -512,15 +1109,235 @@ pub fn burn(
             ]
                 .concat(),
         )
+        .add_submessages(refund)
         .add_attributes(
             vec![
-                vec![
-                    attr("action", "burn"),
-                    attr("position_idx", position_idx.to_string()),
-                    attr("burn_amount", asset.to_string()),
-                ],
+                vec![attr(ACTION, "burn")],
+                position_attributes(deps.as_ref(), &position, asset_price, collateral_price)?,
+                asset_attributes(None, &asset),
                 attributes,
             ]
                 .concat(),
         ))
 }
+
+pub fn query_underwater_positions(
+    deps: Deps,
+    asset_token: String,
+    collateral: AssetInfo,
+    page: Option<PageRequest>,
+) -> StdResult<PositionsResponse> {
+    let mut ctx = Context::load(deps.storage)?.with_price_feed(PriceFeed::Liquidation);
+    let page = page.unwrap_or_default();
+    let limit = ctx
+        .config
+        .query_limits
+        .underwater_positions
+        .resolve(page.limit);
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let asset_config: AssetConfig = ctx.asset_config(deps.storage, &asset_token_raw)?;
+    let asset_info_raw = AssetInfoRaw::Token {
+        contract_addr: asset_token_raw,
+    };
+    let collateral_info_raw: AssetInfoRaw = collateral.to_raw(deps.api)?;
+
+    let (asset_price, (collateral_price, mut collateral_multiplier, _collateral_is_revoked)) =
+        ctx.prices(deps, &asset_info_raw, &collateral_info_raw, false)?;
+
+    // ignore multiplier for delisted assets
+    if asset_config.end_price.is_some() {
+        collateral_multiplier = Decimal::one();
+    }
+
+    // positions holding less collateral per unit of asset are below the minimum collateral ratio
+    let min_collateral_per_asset = decimal_multiplication(
+        decimal_multiplication(
+            decimal_division(asset_price, collateral_price),
+            asset_config.min_collateral_ratio,
+        ),
+        collateral_multiplier,
+    );
+
+    let scan: CollateralRatioScan = read_positions_with_collateral_ratio_indexer(
+        deps.storage,
+        &asset_info_raw,
+        &collateral_info_raw,
+        page.parse_cursor()?,
+        // one bucket of slack for the rounding of the log, the filter is exact
+        collateral_ratio_bucket_of(min_collateral_per_asset).saturating_add(1),
+        limit,
+        page.order,
+        |position| position.asset.amount * min_collateral_per_asset > position.collateral.amount,
+    )?;
+    // the cursor is the last entry visited, so a page of unmatched entries still advances
+    let page = if scan.truncated {
+        PageResponse::truncated(scan.last_key)
+    } else {
+        PageResponse::new(scan.last_key, scan.positions.len(), limit)
+    };
+
+    let positions = scan
+        .positions
+        .into_iter()
+        .map(|position| to_position_response(deps, position))
+        .collect::<StdResult<Vec<PositionResponse>>>()?;
+
+    Ok(PositionsResponse { positions, page })
+}
+
+pub fn prune(deps: DepsMut, env: Env, limit: Option<u32>) -> StdResult<Response> {
+    let (messages, pruned_count) = prune_closed_positions(deps, &env, limit)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr(ACTION, "prune"),
+        attr(PRUNED_COUNT, pruned_count.to_string()),
+    ]))
+}
+
+/// Archives and removes a page of positions closed past the retention period,
+/// returns the position NFT burns and the number of positions pruned
+pub fn prune_closed_positions(
+    deps: DepsMut,
+    env: &Env,
+    limit: Option<u32>,
+) -> StdResult<(Vec<CosmosMsg>, usize)> {
+    let config: Config = read_config(deps.storage)?;
+    let closed_before = env
+        .block
+        .time
+        .seconds()
+        .saturating_sub(config.closed_position_retention);
+
+    let limit = config.query_limits.prune_closed_positions.resolve(limit);
+    let closed_positions: Vec<Position> =
+        read_closed_positions(deps.storage, closed_before, limit)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for position in closed_positions.iter() {
+        if let Some(msg) = position_nft_burn_msg(deps.as_ref(), position)? {
+            messages.push(msg);
+        }
+        store_archived_position(
+            deps.storage,
+            &ArchivedPosition {
+                idx: position.idx,
+                owner: position.owner.clone(),
+                collateral: position.collateral.info.clone(),
+                asset: position.asset.info.clone(),
+                closed_at: position.closed_at.unwrap_or_default(),
+            },
+        )?;
+        remove_position(deps.storage, position.idx)?;
+    }
+
+    Ok((messages, closed_positions.len()))
+}
+
+pub fn transfer_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position_idx: Uint128,
+    recipient: Addr,
+) -> StdResult<Response> {
+    let mut position: Position = read_position(deps.storage, position_idx)?;
+    let position_nft = match &position.position_nft {
+        Some(position_nft) => position_nft,
+        None => return Err(StdError::generic_err("Position is not tokenized")),
+    };
+    // only the contract the position was minted on holds its NFT
+    if *position_nft != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    assert_not_frozen(deps.storage, position_idx)?;
+
+    let previous_owner = deps.api.addr_humanize(&position.owner)?;
+    position.owner = deps.api.addr_canonicalize(recipient.as_str())?;
+    store_position(deps.storage, position_idx, &position)?;
+    append_journal_entry(
+        deps.storage,
+        &position,
+        &journal_entry(&env, "transfer_position", &position),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "transfer_position"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr(OWNER, recipient),
+        attr(PREVIOUS_OWNER, previous_owner),
+    ]))
+}
+
+pub fn set_position_label(
+    deps: DepsMut,
+    info: MessageInfo,
+    position_idx: Uint128,
+    label: Option<String>,
+) -> StdResult<Response> {
+    let mut position: Position = read_position(deps.storage, position_idx)?;
+    if position.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    if let Some(label) = &label {
+        assert_position_label(label)?;
+    }
+
+    position.label = label.clone();
+    store_position(deps.storage, position_idx, &position)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "set_position_label"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr(OWNER, info.sender),
+        attr("label", label.unwrap_or_default()),
+    ]))
+}
+
+// mints the NFT of a newly created tokenized position to its owner
+pub fn position_nft_mint_msg(deps: Deps, position: &Position) -> StdResult<Option<CosmosMsg>> {
+    let position_nft = match &position.position_nft {
+        Some(position_nft) => position_nft,
+        None => return Ok(None),
+    };
+
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(position_nft)?.to_string(),
+        funds: vec![],
+        msg: to_binary(&PositionNftExecuteMsg::Mint {
+            token_id: position.idx.to_string(),
+            owner: deps.api.addr_humanize(&position.owner)?.to_string(),
+        })?,
+    })))
+}
+
+// burns the NFT of a tokenized position that is about to be removed
+pub fn position_nft_burn_msg(deps: Deps, position: &Position) -> StdResult<Option<CosmosMsg>> {
+    let position_nft = match &position.position_nft {
+        Some(position_nft) => position_nft,
+        None => return Ok(None),
+    };
+
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(position_nft)?.to_string(),
+        funds: vec![],
+        msg: to_binary(&PositionNftExecuteMsg::Burn {
+            token_id: position.idx.to_string(),
+        })?,
+    })))
+}
+
+pub fn query_archived_position(
+    deps: Deps,
+    position_idx: Uint128,
+) -> StdResult<ArchivedPositionResponse> {
+    let archived_position: ArchivedPosition = read_archived_position(deps.storage, position_idx)?;
+    Ok(ArchivedPositionResponse {
+        idx: archived_position.idx,
+        owner: deps
+            .api
+            .addr_humanize(&archived_position.owner)?
+            .to_string(),
+        collateral: archived_position.collateral.to_normal(deps.api)?,
+        asset: archived_position.asset.to_normal(deps.api)?,
+        closed_at: archived_position.closed_at,
+    })
+}
@@ -0,0 +1,40 @@
+use cosmwasm_std::{attr, DepsMut, MessageInfo, Response, StdError, StdResult};
+use melange_protocol::{attributes::ACTION, mint::DualPriceFeeds};
+
+use crate::state::{read_asset_config, read_config, store_asset_config, AssetConfig, Config};
+
+pub fn update_asset_price_feeds(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_token: String,
+    price_feeds: Option<DualPriceFeeds>,
+) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let mut asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+
+    let mut attributes = vec![
+        attr(ACTION, "update_asset_price_feeds"),
+        attr("asset_token", asset_token),
+    ];
+    if let Some(price_feeds) = &price_feeds {
+        if price_feeds.mint_feed.is_empty() || price_feeds.liquidation_feed.is_empty() {
+            return Err(StdError::generic_err("Price feeds must not be empty"));
+        }
+        // a basket is priced from its components, there is no feed to replace
+        if asset_config.basket.is_some() {
+            return Err(StdError::generic_err("Asset is priced as a basket"));
+        }
+
+        attributes.push(attr("mint_feed", &price_feeds.mint_feed));
+        attributes.push(attr("liquidation_feed", &price_feeds.liquidation_feed));
+    }
+    asset_config.price_feeds = price_feeds;
+    store_asset_config(deps.storage, &asset_token_raw, &asset_config)?;
+
+    Ok(Response::new().add_attributes(attributes))
+}
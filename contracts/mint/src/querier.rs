@@ -1,27 +1,31 @@
 use cosmwasm_std::{
-    to_binary, Addr, Decimal, Deps, QuerierWrapper, QueryRequest, StdResult, WasmQuery,
+    to_binary, Addr, Decimal, Deps, QuerierWrapper, QueryRequest, StdResult, Uint128, WasmQuery,
 };
 
 use crate::{
     math::decimal_division,
-    state::{read_config, read_fixed_price, Config},
+    state::Config,
     asset::AssetInfoRaw
 };
 use melange_protocol::collateral_oracle::{
-    CollateralInfoResponse, CollateralPriceResponse, QueryMsg as CollateralOracleQueryMsg,
+    CollateralInfoResponse, CollateralPriceResponse, CollateralPricesResponse,
+    QueryMsg as CollateralOracleQueryMsg,
 };
-use melange_protocol::asset::AssetInfoRaw;
+use melange_protocol::asset::{query_balance, query_token_balance, AssetInfo, AssetInfoRaw};
+use melange_protocol::mint::FeeDiscountTier;
+use melange_protocol::staking::{QueryMsg as StakingQueryMsg, StakerResponse};
 use sei_cosmwasm::PriceResponse;
 
 const PRICE_EXPIRE_TIME: u64 = 60;
 
+// end_price is the stored price of the collateral if it is a migrated mAsset
 pub fn load_collateral_info(
     deps: Deps,
-    collateral_oracle: Addr,
+    config: &Config,
     collateral: &AssetInfoRaw,
+    end_price: Option<Decimal>,
     check_expire: bool,
 ) -> StdResult<(Decimal, Decimal, bool)> {
-    let config: Config = read_config(deps.storage)?;
     let collateral_denom: String = (collateral.to_normal(deps.api)?).to_string();
 
     // base collateral
@@ -29,9 +33,9 @@ pub fn load_collateral_info(
         return Ok((Decimal::one(), Decimal::one(), false));
     }
 
-    // check if the collateral is a revoked mAsset
-    let end_price = read_fixed_price(deps.storage, collateral);
+    let collateral_oracle: Addr = deps.api.addr_humanize(&config.collateral_oracle)?;
 
+    // check if the collateral is a revoked mAsset
     if let Some(end_price) = end_price {
         // load collateral_multiplier from collateral oracle
         // if asset is revoked, no need to check for old price
@@ -86,25 +90,83 @@ pub fn query_collateral(
     Ok((res.rate, res.multiplier, res.is_revoked))
 }
 
+// queries the collateral oracle for the rates of several assets in one round trip
+pub fn query_prices(
+    querier: &QuerierWrapper,
+    collateral_oracle: Addr,
+    assets: Vec<AssetInfo>,
+    check_expire: bool,
+) -> StdResult<Vec<CollateralPriceResponse>> {
+    let timeframe: Option<u64> = if check_expire {
+        Some(PRICE_EXPIRE_TIME)
+    } else {
+        None
+    };
+    let res: CollateralPricesResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: collateral_oracle.to_string(),
+        msg: to_binary(&CollateralOracleQueryMsg::Prices { assets, timeframe })?,
+    }))?;
+
+    Ok(res.prices)
+}
+
+// queries the staking contract for the amount of MEL staked by the address
+pub fn query_staked_balance(
+    querier: &QuerierWrapper,
+    staking: Addr,
+    address: Addr,
+) -> StdResult<Uint128> {
+    let res: StakerResponse = querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: staking.to_string(),
+        msg: to_binary(&StakingQueryMsg::Staker {
+            address: address.to_string(),
+        })?,
+    }))?;
+
+    Ok(res.balance)
+}
+
+// native or cw20 balance of the address
+pub fn query_asset_balance(
+    querier: &QuerierWrapper,
+    info: &AssetInfo,
+    address: Addr,
+) -> StdResult<Uint128> {
+    match info {
+        AssetInfo::NativeToken { denom } => query_balance(querier, address, denom.to_string()),
+        AssetInfo::Token { contract_addr } => {
+            query_token_balance(querier, Addr::unchecked(contract_addr), address)
+        }
+    }
+}
+
+// returns the fee multiplier of the highest tier reached by the staked amount
+pub fn select_fee_multiplier(tiers: &[FeeDiscountTier], staked: Uint128) -> Decimal {
+    tiers
+        .iter()
+        .rev()
+        .find(|tier| staked >= tier.min_staked)
+        .map(|tier| tier.fee_multiplier)
+        .unwrap_or_else(Decimal::one)
+}
+
+// end_price is the stored price of the asset if it is migrated
 pub fn load_asset_price(
     deps: Deps,
-    oracle: Addr,
+    config: &Config,
     asset: &AssetInfoRaw,
+    end_price: Option<Decimal>,
     check_expire: bool,
 ) -> StdResult<Decimal> {
-    let config: Config = read_config(deps.storage)?;
-
-    // check if the asset has a stored end_price or pre_ipo_price
-    let stored_price = read_fixed_price(deps.storage, asset);
-
-    let price: Decimal = if let Some(stored_price) = stored_price {
-        stored_price
+    let price: Decimal = if let Some(end_price) = end_price {
+        end_price
     } else {
         let asset_denom: String = (asset.to_normal(deps.api)?).to_string();
         if asset_denom == config.base_denom {
             Decimal::one()
         } else {
             // fetch price from oracle
+            let oracle: Addr = deps.api.addr_humanize(&config.oracle)?;
             query_price(&deps.querier, oracle, asset_denom, None, check_expire)?
         }
     };
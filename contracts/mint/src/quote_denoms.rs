@@ -0,0 +1,89 @@
+use cosmwasm_std::{CosmosMsg, Decimal, Deps, StdError, StdResult, Uint128};
+use melange_protocol::{
+    asset::{Asset, AssetInfo},
+    mint::PositionValueResponse,
+};
+
+use crate::{
+    context::Context,
+    leverage::swap_msg,
+    math::{decimal_division, decimal_subtraction},
+    state::{read_position, Config, Position},
+};
+
+/// Sends a protocol fee to the collector. A fee charged in one of the quote denoms is sold
+/// for base_denom on the dex on the way, so the collector only receives base_denom; the
+/// sale must return at least the oracle value of the fee less `max_spread`, which the
+/// sender chooses. `fee_price` is the price of the fee asset in base_denom. Returns the
+/// fee as the collector is credited it, for a sold fee the minimum the swap must return
+pub fn protocol_fee_msg(
+    deps: Deps,
+    config: &Config,
+    fee: &Asset,
+    fee_price: Decimal,
+    max_spread: Option<Decimal>,
+) -> StdResult<(CosmosMsg, Asset)> {
+    let collector = deps.api.addr_humanize(&config.collector)?;
+    let is_quote_denom = match &fee.info {
+        AssetInfo::NativeToken { denom } => config.quote_denoms.contains(denom),
+        AssetInfo::Token { .. } => false,
+    };
+    if !is_quote_denom {
+        return Ok((fee.clone().into_msg(&deps.querier, collector)?, fee.clone()));
+    }
+
+    let dex_contract = match &config.dex_contract {
+        Some(dex_contract) => deps.api.addr_humanize(dex_contract)?,
+        None => return Err(StdError::generic_err("No dex contract configured")),
+    };
+    let max_spread = max_spread.ok_or_else(|| {
+        StdError::generic_err("max_spread is required to sell a quote denom protocol fee")
+    })?;
+    if max_spread >= Decimal::one() {
+        return Err(StdError::generic_err("max_spread must be lower than 1"));
+    }
+
+    let collected_fee = Asset {
+        info: AssetInfo::NativeToken {
+            denom: config.base_denom.clone(),
+        },
+        amount: fee.amount * fee_price * decimal_subtraction(Decimal::one(), max_spread),
+    };
+    let msg = swap_msg(
+        &dex_contract,
+        fee,
+        collected_fee.info.clone(),
+        collected_fee.amount,
+        Some(&collector),
+    )?;
+    Ok((CosmosMsg::Wasm(msg), collected_fee))
+}
+
+pub fn query_position_value(
+    deps: Deps,
+    position_idx: Uint128,
+    quote_denom: Option<String>,
+) -> StdResult<PositionValueResponse> {
+    let mut ctx = Context::load(deps.storage)?;
+    let quote_denom = quote_denom.unwrap_or_else(|| ctx.config.base_denom.clone());
+    let quote_rate = ctx.quote_rate(deps, &quote_denom, false)?;
+    if quote_rate.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "Quote denom {} has no price",
+            quote_denom
+        )));
+    }
+
+    let position: Position = read_position(deps.storage, position_idx)?;
+    let (asset_price, (collateral_price, _, _)) =
+        ctx.prices(deps, &position.asset.info, &position.collateral.info, false)?;
+
+    Ok(PositionValueResponse {
+        position_idx,
+        collateral_value: position.collateral.amount
+            * decimal_division(collateral_price, quote_rate),
+        debt_value: position.asset.amount * decimal_division(asset_price, quote_rate),
+        quote_denom,
+        quote_rate,
+    })
+}
@@ -0,0 +1,289 @@
+use cosmwasm_std::{
+    attr, Addr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128,
+};
+use melange_protocol::{
+    attributes::{ACTION, OWNER, POSITION_IDX, SESSION_KEY},
+    mint::{SessionKeyResponse, SessionKeysResponse},
+};
+
+use crate::state::{
+    read_position, read_session_key, read_session_keys, remove_session_key, store_session_key,
+    Position, SessionKey, MAX_SESSION_KEYS,
+};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// Position actions a session key can be spent on
+pub enum SessionAction {
+    Deposit,
+    Burn,
+}
+
+/// Lets `key` deposit to and burn against the position until `expires_at`, replacing any
+/// previous grant of the same key along with its spent amounts
+#[allow(clippy::too_many_arguments)]
+pub fn authorize_session_key(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position_idx: Uint128,
+    key: Addr,
+    expires_at: u64,
+    daily_deposit_cap: Uint128,
+    daily_burn_cap: Uint128,
+) -> StdResult<Response> {
+    let position: Position = read_position(deps.storage, position_idx)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if position.owner != sender_raw {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    if key == info.sender {
+        return Err(StdError::generic_err(
+            "The owner cannot be its own session key",
+        ));
+    }
+    if expires_at <= env.block.time.seconds() {
+        return Err(StdError::generic_err(
+            "Session key expiry must be in the future",
+        ));
+    }
+    let key_raw = deps.api.addr_canonicalize(key.as_str())?;
+    if read_session_key(deps.storage, position_idx, &key_raw)?.is_none()
+        && read_session_keys(deps.storage, position_idx)?.len() >= MAX_SESSION_KEYS
+    {
+        return Err(StdError::generic_err(format!(
+            "A position can have at most {} session keys",
+            MAX_SESSION_KEYS
+        )));
+    }
+
+    store_session_key(
+        deps.storage,
+        position_idx,
+        &key_raw,
+        &SessionKey {
+            granter: sender_raw,
+            expires_at,
+            daily_deposit_cap,
+            daily_burn_cap,
+            day: env.block.time.seconds() / SECONDS_PER_DAY,
+            deposited: Uint128::zero(),
+            burned: Uint128::zero(),
+        },
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "authorize_session_key"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr(OWNER, info.sender),
+        attr(SESSION_KEY, key),
+        attr("expires_at", expires_at.to_string()),
+    ]))
+}
+
+pub fn revoke_session_key(
+    deps: DepsMut,
+    info: MessageInfo,
+    position_idx: Uint128,
+    key: Addr,
+) -> StdResult<Response> {
+    let position: Position = read_position(deps.storage, position_idx)?;
+    if position.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    remove_session_key(
+        deps.storage,
+        position_idx,
+        &deps.api.addr_canonicalize(key.as_str())?,
+    );
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "revoke_session_key"),
+        attr(POSITION_IDX, position_idx.to_string()),
+        attr(OWNER, info.sender),
+        attr(SESSION_KEY, key),
+    ]))
+}
+
+/// Checks `sender` holds a live session key of the position granted by its current owner
+/// and books `amount` against the daily cap of the action
+pub fn spend_session_key(
+    deps: DepsMut,
+    env: &Env,
+    position: &Position,
+    sender: &Addr,
+    action: SessionAction,
+    amount: Uint128,
+) -> StdResult<()> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let mut session_key = match read_session_key(deps.storage, position.idx, &sender_raw)? {
+        // grants made by a previous owner of the position are void
+        Some(session_key) if session_key.granter == position.owner => session_key,
+        _ => return Err(StdError::generic_err("unauthorized")),
+    };
+    if session_key.expires_at <= env.block.time.seconds() {
+        return Err(StdError::generic_err("Session key expired"));
+    }
+
+    book_spend(&mut session_key, env.block.time.seconds(), action, amount)?;
+
+    store_session_key(deps.storage, position.idx, &sender_raw, &session_key)
+}
+
+/// Adds `amount` to the spent amount of the action, starting over when `now`
+/// falls on a later day than the one the spent amounts belong to
+fn book_spend(
+    session_key: &mut SessionKey,
+    now: u64,
+    action: SessionAction,
+    amount: Uint128,
+) -> StdResult<()> {
+    let day = now / SECONDS_PER_DAY;
+    if session_key.day != day {
+        session_key.day = day;
+        session_key.deposited = Uint128::zero();
+        session_key.burned = Uint128::zero();
+    }
+
+    let (spent, cap) = match action {
+        SessionAction::Deposit => (&mut session_key.deposited, session_key.daily_deposit_cap),
+        SessionAction::Burn => (&mut session_key.burned, session_key.daily_burn_cap),
+    };
+    *spent = spent.checked_add(amount)?;
+    if *spent > cap {
+        return Err(StdError::generic_err("Session key daily cap exceeded"));
+    }
+
+    Ok(())
+}
+
+pub fn query_session_keys(deps: Deps, position_idx: Uint128) -> StdResult<SessionKeysResponse> {
+    let position: Position = read_position(deps.storage, position_idx)?;
+    let keys = read_session_keys(deps.storage, position_idx)?
+        .into_iter()
+        .filter(|(_, session_key)| session_key.granter == position.owner)
+        .map(|(key, session_key)| {
+            Ok(SessionKeyResponse {
+                key: deps.api.addr_humanize(&key)?.to_string(),
+                expires_at: session_key.expires_at,
+                daily_deposit_cap: session_key.daily_deposit_cap,
+                daily_burn_cap: session_key.daily_burn_cap,
+                day: session_key.day,
+                deposited: session_key.deposited,
+                burned: session_key.burned,
+            })
+        })
+        .collect::<StdResult<Vec<SessionKeyResponse>>>()?;
+
+    Ok(SessionKeysResponse { position_idx, keys })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::CanonicalAddr;
+
+    const DAY_START: u64 = 100 * SECONDS_PER_DAY;
+
+    fn session_key() -> SessionKey {
+        SessionKey {
+            granter: CanonicalAddr::from(vec![1u8; 20]),
+            expires_at: DAY_START + 10 * SECONDS_PER_DAY,
+            daily_deposit_cap: Uint128::new(100),
+            daily_burn_cap: Uint128::new(50),
+            day: DAY_START / SECONDS_PER_DAY,
+            deposited: Uint128::zero(),
+            burned: Uint128::zero(),
+        }
+    }
+
+    #[test]
+    fn caps_apply_within_a_day() {
+        let mut session_key = session_key();
+        book_spend(
+            &mut session_key,
+            DAY_START,
+            SessionAction::Deposit,
+            Uint128::new(60),
+        )
+        .unwrap();
+        book_spend(
+            &mut session_key,
+            DAY_START + SECONDS_PER_DAY - 1,
+            SessionAction::Deposit,
+            Uint128::new(40),
+        )
+        .unwrap();
+        assert_eq!(session_key.deposited, Uint128::new(100));
+
+        // the last second of the day still books against the same cap
+        let err = book_spend(
+            &mut session_key,
+            DAY_START + SECONDS_PER_DAY - 1,
+            SessionAction::Deposit,
+            Uint128::new(1),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Session key daily cap exceeded"));
+
+        // deposits and burns have separate caps
+        book_spend(
+            &mut session_key,
+            DAY_START,
+            SessionAction::Burn,
+            Uint128::new(50),
+        )
+        .unwrap();
+        assert!(book_spend(
+            &mut session_key,
+            DAY_START,
+            SessionAction::Burn,
+            Uint128::new(1)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn spent_amounts_roll_over_at_day_boundary() {
+        let mut session_key = session_key();
+        book_spend(
+            &mut session_key,
+            DAY_START,
+            SessionAction::Deposit,
+            Uint128::new(100),
+        )
+        .unwrap();
+        book_spend(
+            &mut session_key,
+            DAY_START,
+            SessionAction::Burn,
+            Uint128::new(20),
+        )
+        .unwrap();
+
+        // the first second of the next day starts both caps over
+        let next_day = DAY_START + SECONDS_PER_DAY;
+        book_spend(
+            &mut session_key,
+            next_day,
+            SessionAction::Deposit,
+            Uint128::new(100),
+        )
+        .unwrap();
+        assert_eq!(session_key.day, next_day / SECONDS_PER_DAY);
+        assert_eq!(session_key.deposited, Uint128::new(100));
+        assert_eq!(session_key.burned, Uint128::zero());
+
+        // skipping days rolls over too
+        book_spend(
+            &mut session_key,
+            next_day + 3 * SECONDS_PER_DAY,
+            SessionAction::Burn,
+            Uint128::new(50),
+        )
+        .unwrap();
+        assert_eq!(session_key.deposited, Uint128::zero());
+        assert_eq!(session_key.burned, Uint128::new(50));
+    }
+}
@@ -0,0 +1,268 @@
+use cosmwasm_std::{Coin, Deps, DepsMut, Env, MessageInfo, Order, Record, StdResult, Storage};
+use melange_protocol::mint::{ExecuteMsg, SimulateExecuteResponse};
+use std::{collections::BTreeMap, iter::Peekable, ops::Bound};
+
+use crate::contract::execute;
+
+/// Runs the execute handler of `msg` as `sender` sending `funds`, on a copy-on-write view
+/// of the contract storage that is dropped afterwards. Assertions fail the query with the
+/// error the transaction would fail with; replies of submessages do not run
+pub fn query_simulate_execute(
+    deps: Deps,
+    env: Env,
+    sender: String,
+    funds: Vec<Coin>,
+    msg: ExecuteMsg,
+) -> StdResult<SimulateExecuteResponse> {
+    let info = MessageInfo {
+        sender: deps.api.addr_validate(&sender)?,
+        funds,
+    };
+    let mut storage = SimulatedStorage::new(deps.storage);
+    let response = execute(
+        DepsMut {
+            storage: &mut storage,
+            api: deps.api,
+            querier: deps.querier,
+        },
+        env,
+        info,
+        msg,
+    )?;
+
+    Ok(SimulateExecuteResponse {
+        messages: response.messages,
+        attributes: response.attributes,
+        events: response.events,
+        data: response.data,
+    })
+}
+
+/// a simulated write, `None` for a removal
+type Write = (Vec<u8>, Option<Vec<u8>>);
+
+/// Reads through to the contract storage, keeps writes in memory.
+/// A removal is kept as a write too, so it hides the stored value
+struct SimulatedStorage<'a> {
+    base: &'a dyn Storage,
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> SimulatedStorage<'a> {
+    fn new(base: &'a dyn Storage) -> Self {
+        SimulatedStorage {
+            base,
+            writes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a> Storage for SimulatedStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.writes.get(key) {
+            Some(value) => value.clone(),
+            None => self.base.get(key),
+        }
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let bounds = (
+            start.map_or(Bound::Unbounded, |start| Bound::Included(start.to_vec())),
+            end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec())),
+        );
+        // an empty or inverted range is empty, BTreeMap::range would panic on it
+        let mut writes: Vec<Write> = match (start, end) {
+            (Some(start), Some(end)) if start >= end => vec![],
+            _ => self
+                .writes
+                .range(bounds)
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        };
+        if order == Order::Descending {
+            writes.reverse();
+        }
+
+        Box::new(MergedRange {
+            base: self.base.range(start, end, order).peekable(),
+            writes: writes.into_iter().peekable(),
+            order,
+        })
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes.insert(key.to_vec(), None);
+    }
+}
+
+/// Stored records merged with the simulated writes in range order,
+/// a write replacing or hiding the stored record of the same key
+struct MergedRange<'a> {
+    base: Peekable<Box<dyn Iterator<Item = Record> + 'a>>,
+    writes: Peekable<std::vec::IntoIter<Write>>,
+    order: Order,
+}
+
+impl<'a> Iterator for MergedRange<'a> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        loop {
+            let write_first = match (self.base.peek(), self.writes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some((base_key, _)), Some((write_key, _))) => {
+                    if base_key == write_key {
+                        self.base.next();
+                        true
+                    } else if self.order == Order::Ascending {
+                        write_key < base_key
+                    } else {
+                        write_key > base_key
+                    }
+                }
+            };
+
+            if !write_first {
+                return self.base.next();
+            }
+            if let Some((key, Some(value))) = self.writes.next() {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::MemoryStorage;
+
+    fn stored() -> MemoryStorage {
+        let mut base = MemoryStorage::new();
+        base.set(b"a", b"1");
+        base.set(b"c", b"3");
+        base.set(b"e", b"5");
+        base
+    }
+
+    fn records(
+        storage: &dyn Storage,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Vec<Record> {
+        storage.range(start, end, order).collect()
+    }
+
+    fn record(key: &[u8], value: &[u8]) -> Record {
+        (key.to_vec(), value.to_vec())
+    }
+
+    #[test]
+    fn writes_are_merged_in_order() {
+        let base = stored();
+        let mut storage = SimulatedStorage::new(&base);
+        storage.set(b"b", b"2");
+        storage.set(b"f", b"6");
+
+        assert_eq!(
+            records(&storage, None, None, Order::Ascending),
+            vec![
+                record(b"a", b"1"),
+                record(b"b", b"2"),
+                record(b"c", b"3"),
+                record(b"e", b"5"),
+                record(b"f", b"6"),
+            ]
+        );
+        assert_eq!(
+            records(&storage, None, None, Order::Descending),
+            vec![
+                record(b"f", b"6"),
+                record(b"e", b"5"),
+                record(b"c", b"3"),
+                record(b"b", b"2"),
+                record(b"a", b"1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_replaces_stored_value() {
+        let base = stored();
+        let mut storage = SimulatedStorage::new(&base);
+        storage.set(b"c", b"30");
+
+        assert_eq!(storage.get(b"c"), Some(b"30".to_vec()));
+        assert_eq!(
+            records(&storage, None, None, Order::Ascending),
+            vec![record(b"a", b"1"), record(b"c", b"30"), record(b"e", b"5")]
+        );
+        // the contract storage is left untouched
+        assert_eq!(base.get(b"c"), Some(b"3".to_vec()));
+    }
+
+    #[test]
+    fn removal_hides_stored_value() {
+        let base = stored();
+        let mut storage = SimulatedStorage::new(&base);
+        storage.remove(b"c");
+        storage.set(b"d", b"4");
+        storage.remove(b"d");
+
+        assert_eq!(storage.get(b"c"), None);
+        assert_eq!(
+            records(&storage, None, None, Order::Ascending),
+            vec![record(b"a", b"1"), record(b"e", b"5")]
+        );
+        assert_eq!(
+            records(&storage, None, None, Order::Descending),
+            vec![record(b"e", b"5"), record(b"a", b"1")]
+        );
+    }
+
+    #[test]
+    fn range_bounds_apply_to_writes() {
+        let base = stored();
+        let mut storage = SimulatedStorage::new(&base);
+        storage.set(b"b", b"2");
+        storage.set(b"e", b"50");
+
+        // start is inclusive, end is exclusive
+        assert_eq!(
+            records(&storage, Some(b"b"), Some(b"e"), Order::Ascending),
+            vec![record(b"b", b"2"), record(b"c", b"3")]
+        );
+        assert_eq!(
+            records(&storage, Some(b"b"), Some(b"e"), Order::Descending),
+            vec![record(b"c", b"3"), record(b"b", b"2")]
+        );
+    }
+
+    #[test]
+    fn inverted_range_is_empty() {
+        let base = stored();
+        let mut storage = SimulatedStorage::new(&base);
+        storage.set(b"b", b"2");
+
+        assert_eq!(
+            records(&storage, Some(b"d"), Some(b"b"), Order::Ascending),
+            vec![]
+        );
+        assert_eq!(
+            records(&storage, Some(b"c"), Some(b"c"), Order::Descending),
+            vec![]
+        );
+    }
+}
@@ -1,19 +1,32 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, Decimal, StdError, StdResult, Storage, Uint128};
+use cosmwasm_std::{
+    CanonicalAddr, Decimal, IbcEndpoint, Order, StdError, StdResult, Storage, Uint128, Uint256,
+};
 
 use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use melange_protocol::common::OrderBy;
+use melange_protocol::mint::{DualPriceFeeds, FeeDiscountTier, QueryLimits};
 use melange_protocol::asset::{AssetInfoRaw, AssetRaw};
-use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
 
-pub static PREFIX_ASSET_CONFIG: &[u8] = b"asset_config";
-static PREFIX_POSITION: &[u8] = b"position";
-static PREFIX_INDEX_BY_USER: &[u8] = b"by_user";
-static PREFIX_INDEX_BY_ASSET: &[u8] = b"by_asset";
+use crate::migration::backfilled_until;
+
+const NAMESPACE_ASSET_CONFIG: &str = "asset_config";
+const NAMESPACE_POSITION: &str = "position";
+static PREFIX_FEE_TIER_CACHE: &[u8] = b"fee_tier_cache";
+const DEFAULT_CLOSED_POSITION_RETENTION: u64 = 30 * 86400;
 pub static KEY_CONFIG: &[u8] = b"config";
 static KEY_POSITION_IDX: &[u8] = b"position_idx";
+static KEY_FEE_DISCOUNT_TIERS: &[u8] = b"fee_discount_tiers";
+static KEY_FLASH_MINT: &[u8] = b"flash_mint";
+static KEY_LEVERAGE_LOOP: &[u8] = b"leverage_loop";
+static KEY_DELEVERAGE_LOOP: &[u8] = b"deleverage_loop";
+static KEY_CLOSING_POSITION: &[u8] = b"closing_position";
+static KEY_IBC_RECEIVE: &[u8] = b"ibc_receive";
 
 pub fn store_position_idx(storage: &mut dyn Storage, position_idx: Uint128) -> StdResult<()> {
     singleton(storage, KEY_POSITION_IDX).save(&position_idx)
@@ -33,8 +46,36 @@ pub struct Config {
     pub melange_factory: CanonicalAddr,
     pub lock: CanonicalAddr,
     pub base_denom: String,
+    #[serde(default)]
+    pub quote_denoms: Vec<String>,
     pub token_code_id: u64,
     pub protocol_fee_rate: Decimal,
+    #[serde(default)]
+    pub referral_fee_rate: Decimal,
+    #[serde(default = "default_closed_position_retention")]
+    pub closed_position_retention: u64,
+    #[serde(default)]
+    pub query_limits: QueryLimits,
+    #[serde(default)]
+    pub position_nft: Option<CanonicalAddr>,
+    /// flash mints are disabled while no fee rate is set
+    #[serde(default)]
+    pub flash_mint_fee_rate: Option<Decimal>,
+    #[serde(default)]
+    pub dex_contract: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub mel_token: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub mel_fee_discount: Decimal,
+    #[serde(default)]
+    pub guardian: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub cron: Option<CanonicalAddr>,
+}
+
+/// retention of closed positions for configs stored before it was configurable
+fn default_closed_position_retention() -> u64 {
+    DEFAULT_CLOSED_POSITION_RETENTION
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
@@ -45,72 +86,307 @@ pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
     singleton_read(storage, KEY_CONFIG).load()
 }
 
+pub fn store_fee_discount_tiers(
+    storage: &mut dyn Storage,
+    tiers: &[FeeDiscountTier],
+) -> StdResult<()> {
+    singleton(storage, KEY_FEE_DISCOUNT_TIERS).save(&tiers.to_vec())
+}
+
+pub fn read_fee_discount_tiers(storage: &dyn Storage) -> StdResult<Vec<FeeDiscountTier>> {
+    let tiers: Option<Vec<FeeDiscountTier>> =
+        singleton_read(storage, KEY_FEE_DISCOUNT_TIERS).may_load()?;
+    Ok(tiers.unwrap_or_default())
+}
+
+/// flash mint awaiting repayment, only exists while the borrower callback is executed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlashMint {
+    pub borrower: CanonicalAddr,
+    pub asset_token: CanonicalAddr,
+    pub amount: Uint128,
+    pub fee: Uint128,
+    /// asset balance of the contract before minting
+    pub balance_before: Uint128,
+}
+
+pub fn store_flash_mint(storage: &mut dyn Storage, flash_mint: &FlashMint) -> StdResult<()> {
+    singleton(storage, KEY_FLASH_MINT).save(flash_mint)
+}
+
+pub fn read_flash_mint(storage: &dyn Storage) -> StdResult<Option<FlashMint>> {
+    singleton_read(storage, KEY_FLASH_MINT).may_load()
+}
+
+pub fn remove_flash_mint(storage: &mut dyn Storage) {
+    singleton::<FlashMint>(storage, KEY_FLASH_MINT).remove()
+}
+
+/// leverage loop awaiting the result of its pending swap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeverageLoop {
+    pub position_idx: Uint128,
+    pub initial_collateral: Uint128,
+    pub target_collateral: Uint128,
+    /// collateral ratio every round mints down to
+    pub loop_collateral_ratio: Decimal,
+    pub max_spread: Decimal,
+    pub iterations: u32,
+    /// collateral balance of the contract before the pending swap
+    pub balance_before: Uint128,
+}
+
+pub fn store_leverage_loop(
+    storage: &mut dyn Storage,
+    leverage_loop: &LeverageLoop,
+) -> StdResult<()> {
+    singleton(storage, KEY_LEVERAGE_LOOP).save(leverage_loop)
+}
+
+pub fn read_leverage_loop(storage: &dyn Storage) -> StdResult<Option<LeverageLoop>> {
+    singleton_read(storage, KEY_LEVERAGE_LOOP).may_load()
+}
+
+pub fn remove_leverage_loop(storage: &mut dyn Storage) {
+    singleton::<LeverageLoop>(storage, KEY_LEVERAGE_LOOP).remove()
+}
+
+/// deleverage loop awaiting the result of its pending swap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DeleverageLoop {
+    pub position_idx: Uint128,
+    pub target_ratio: Decimal,
+    pub max_spread: Decimal,
+    pub iterations: u32,
+    /// asset balance of the contract before the pending swap
+    pub balance_before: Uint128,
+}
+
+pub fn store_deleverage_loop(
+    storage: &mut dyn Storage,
+    deleverage_loop: &DeleverageLoop,
+) -> StdResult<()> {
+    singleton(storage, KEY_DELEVERAGE_LOOP).save(deleverage_loop)
+}
+
+pub fn read_deleverage_loop(storage: &dyn Storage) -> StdResult<Option<DeleverageLoop>> {
+    singleton_read(storage, KEY_DELEVERAGE_LOOP).may_load()
+}
+
+pub fn remove_deleverage_loop(storage: &mut dyn Storage) {
+    singleton::<DeleverageLoop>(storage, KEY_DELEVERAGE_LOOP).remove()
+}
+
+/// position being closed with a swap, awaiting the result of the swap
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClosingPosition {
+    pub position_idx: Uint128,
+    /// also bounds the sale of a quote denom protocol fee
+    pub max_spread: Decimal,
+    /// asset balance of the contract before the swap
+    pub balance_before: Uint128,
+}
+
+pub fn store_closing_position(
+    storage: &mut dyn Storage,
+    closing_position: &ClosingPosition,
+) -> StdResult<()> {
+    singleton(storage, KEY_CLOSING_POSITION).save(closing_position)
+}
+
+pub fn read_closing_position(storage: &dyn Storage) -> StdResult<Option<ClosingPosition>> {
+    singleton_read(storage, KEY_CLOSING_POSITION).may_load()
+}
+
+pub fn remove_closing_position(storage: &mut dyn Storage) {
+    singleton::<ClosingPosition>(storage, KEY_CLOSING_POSITION).remove()
+}
+
+/// fee multiplier resolved for a user, valid only within the block it was resolved in
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeTierCache {
+    pub height: u64,
+    pub fee_multiplier: Decimal,
+}
+
+pub fn store_fee_tier_cache(
+    storage: &mut dyn Storage,
+    user: &CanonicalAddr,
+    cache: &FeeTierCache,
+) -> StdResult<()> {
+    let mut cache_bucket: Bucket<FeeTierCache> = Bucket::new(storage, PREFIX_FEE_TIER_CACHE);
+    cache_bucket.save(user.as_slice(), cache)
+}
+
+pub fn read_fee_tier_cache(
+    storage: &dyn Storage,
+    user: &CanonicalAddr,
+) -> StdResult<Option<FeeTierCache>> {
+    let cache_bucket: ReadonlyBucket<FeeTierCache> =
+        ReadonlyBucket::new(storage, PREFIX_FEE_TIER_CACHE);
+    cache_bucket.may_load(user.as_slice())
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AssetConfig {
     pub token: CanonicalAddr,
     pub min_collateral_ratio: Decimal,
     pub end_price: Option<Decimal>,
+    #[serde(default)]
+    pub mint_fee_rate: Decimal,
+    /// overrides the config protocol_fee_rate on burns
+    #[serde(default)]
+    pub burn_fee_rate: Option<Decimal>,
+    /// set by a failed supply check until the owner unpauses it
+    #[serde(default)]
+    pub mint_paused: bool,
+    /// priced as a weighted basket of other assets instead of by its own oracle feed
+    #[serde(default)]
+    pub basket: Option<Vec<BasketComponent>>,
+    /// oracle feeds replacing the asset feed when minting and when liquidating
+    #[serde(default)]
+    pub price_feeds: Option<DualPriceFeeds>,
 }
 
-// check if the asset has either end_price or pre_ipo_price
-pub fn read_fixed_price(storage: &dyn Storage, asset_info: &AssetInfoRaw) -> Option<Decimal> {
-    match asset_info {
-        AssetInfoRaw::Token { contract_addr } => {
-            let asset_bucket: ReadonlyBucket<AssetConfig> =
-                ReadonlyBucket::new(storage, PREFIX_ASSET_CONFIG);
-            let res = asset_bucket.load(contract_addr.as_slice());
-            match res {
-                Ok(data) => {
-                    data.end_price
-                }
-                _ => None,
-            }
-        }
-        _ => None,
-    }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BasketComponent {
+    pub asset_token: CanonicalAddr,
+    pub weight: Decimal,
+}
+
+/// asset configs keyed by asset token, sharing the key layout of the legacy asset config bucket
+const ASSET_CONFIGS: Map<&[u8], AssetConfig> = Map::new(NAMESPACE_ASSET_CONFIG);
+
+pub fn store_asset_config(
+    storage: &mut dyn Storage,
+    asset_token: &CanonicalAddr,
+    asset: &AssetConfig,
+) -> StdResult<()> {
+    ASSET_CONFIGS.save(storage, asset_token.as_slice(), asset)
+}
+
+pub fn read_asset_configs(storage: &dyn Storage) -> StdResult<Vec<AssetConfig>> {
+    ASSET_CONFIGS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+/// asset config of a registered asset, none for any other token
+pub fn may_read_asset_config(
+    storage: &dyn Storage,
+    asset_token: &CanonicalAddr,
+) -> StdResult<Option<AssetConfig>> {
+    ASSET_CONFIGS.may_load(storage, asset_token.as_slice())
 }
 
 pub fn read_asset_config(
     storage: &dyn Storage,
     asset_token: &CanonicalAddr,
 ) -> StdResult<AssetConfig> {
-    let asset_bucket: ReadonlyBucket<AssetConfig> =
-        ReadonlyBucket::new(storage, PREFIX_ASSET_CONFIG);
-    let res = asset_bucket.load(asset_token.as_slice());
+    let res = ASSET_CONFIGS.load(storage, asset_token.as_slice());
     match res {
         Ok(data) => Ok(data),
         _ => Err(StdError::generic_err("no asset data stored")),
     }
 }
 
-/// create position with index
-pub fn create_position(
-    storage: &mut dyn Storage,
-    idx: Uint128,
-    position: &Position,
-) -> StdResult<()> {
-    let mut position_bucket: Bucket<Position> = Bucket::new(storage, PREFIX_POSITION);
-    position_bucket.save(&idx.u128().to_be_bytes(), position)?;
-
-    let mut position_indexer_by_user: Bucket<bool> =
-        Bucket::multilevel(storage, &[PREFIX_INDEX_BY_USER, position.owner.as_slice()]);
-    position_indexer_by_user.save(&idx.u128().to_be_bytes(), &true)?;
-
-    let mut position_indexer_by_asset: Bucket<bool> = Bucket::multilevel(
-        storage,
-        &[PREFIX_INDEX_BY_ASSET, position.asset.info.as_bytes()],
-    );
-    position_indexer_by_asset.save(&idx.u128().to_be_bytes(), &true)?;
-
-    Ok(())
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Position {
     pub idx: Uint128,
     pub owner: CanonicalAddr,
     pub collateral: AssetRaw,
     pub asset: AssetRaw,
+    #[serde(default)]
+    pub referrer: Option<CanonicalAddr>,
+    /// block time the position got fully closed, reset when it is reopened
+    #[serde(default)]
+    pub closed_at: Option<u64>,
+    /// position NFT contract the position was minted on, kept when the configured
+    /// contract changes so transfers and burns stay on the contract holding the NFT
+    #[serde(default)]
+    pub position_nft: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+pub struct PositionIndexes<'a> {
+    pub owner: MultiIndex<'a, Vec<u8>, Position, u128>,
+    pub asset: MultiIndex<'a, Vec<u8>, Position, u128>,
+    // (asset, collateral, collateral ratio bucket)
+    pub collateral_ratio: MultiIndex<'a, (Vec<u8>, Vec<u8>, u64), Position, u128>,
+    // open positions are indexed under u64::MAX
+    pub closed_at: MultiIndex<'a, u64, Position, u128>,
+}
+
+impl<'a> IndexList<Position> for PositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Position>> + '_> {
+        let v: Vec<&dyn Index<Position>> = vec![
+            &self.owner,
+            &self.asset,
+            &self.collateral_ratio,
+            &self.closed_at,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+/// positions keyed by idx, the primary entries share the key layout of the legacy position bucket
+pub fn positions<'a>() -> IndexedMap<'a, u128, Position, PositionIndexes<'a>> {
+    let indexes = PositionIndexes {
+        owner: MultiIndex::new(
+            |_, position| position.owner.to_vec(),
+            NAMESPACE_POSITION,
+            "position__owner",
+        ),
+        asset: MultiIndex::new(
+            |_, position| position.asset.info.as_bytes().to_vec(),
+            NAMESPACE_POSITION,
+            "position__asset",
+        ),
+        collateral_ratio: MultiIndex::new(
+            |_, position| {
+                (
+                    position.asset.info.as_bytes().to_vec(),
+                    position.collateral.info.as_bytes().to_vec(),
+                    collateral_ratio_bucket(position.collateral.amount, position.asset.amount),
+                )
+            },
+            NAMESPACE_POSITION,
+            "position__collateral_ratio",
+        ),
+        closed_at: MultiIndex::new(
+            |_, position| position.closed_at.unwrap_or(u64::MAX),
+            NAMESPACE_POSITION,
+            "position__closed_at",
+        ),
+    };
+    IndexedMap::new(NAMESPACE_POSITION, indexes)
+}
+
+/// referred positions keyed by (referrer, idx), kept apart from the position indexes
+/// so positions without a referrer are not indexed at all
+const REFERRED_POSITIONS: Map<(&[u8], u128), bool> = Map::new("referred_position");
+
+pub fn store_referred_position(storage: &mut dyn Storage, position: &Position) -> StdResult<()> {
+    match &position.referrer {
+        Some(referrer) => {
+            REFERRED_POSITIONS.save(storage, (referrer.as_slice(), position.idx.u128()), &true)
+        }
+        None => Ok(()),
+    }
+}
+
+/// create position with index
+pub fn create_position(
+    storage: &mut dyn Storage,
+    idx: Uint128,
+    position: &Position,
+) -> StdResult<()> {
+    store_position(storage, idx, position)
 }
 
 /// store position with idx
@@ -119,36 +395,281 @@ pub fn store_position(
     idx: Uint128,
     position: &Position,
 ) -> StdResult<()> {
-    let mut position_bucket: Bucket<Position> = Bucket::new(storage, PREFIX_POSITION);
-    position_bucket.save(&idx.u128().to_be_bytes(), position)?;
-    Ok(())
+    let old = positions().may_load(storage, idx.u128())?;
+    update_asset_totals(storage, idx, old.as_ref(), Some(position))?;
+    // the referrer is set when the position is opened and never changes
+    if old.is_none() {
+        store_referred_position(storage, position)?;
+    }
+    positions().replace(storage, idx.u128(), Some(position), old.as_ref())
 }
 
 /// read position from store with position idx
 pub fn read_position(storage: &dyn Storage, idx: Uint128) -> StdResult<Position> {
-    let position_bucket: ReadonlyBucket<Position> = ReadonlyBucket::new(storage, PREFIX_POSITION);
-    position_bucket.load(&idx.u128().to_be_bytes())
+    positions().load(storage, idx.u128())
+}
+
+// hard cap on the entries a single position read visits, whatever the configured query limits
+const MAX_POSITION_SCAN: usize = 100;
+
+/// positions read by a capped scan
+pub struct PositionScan {
+    pub positions: Vec<Position>,
+    /// the scan stopped at `MAX_POSITION_SCAN` with entries left to read
+    pub truncated: bool,
+}
+
+fn scan_positions(
+    mut iter: impl Iterator<Item = StdResult<(u128, Position)>>,
+    limit: usize,
+) -> StdResult<PositionScan> {
+    let cap = limit.min(MAX_POSITION_SCAN);
+    let positions = iter
+        .by_ref()
+        .take(cap)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<Position>>>()?;
+
+    Ok(PositionScan {
+        truncated: cap < limit && iter.next().is_some(),
+        positions,
+    })
 }
 
-// settings for pagination
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
+/// `limit` is expected to be resolved against the configured `QueryLimits` already,
+/// reads past `MAX_POSITION_SCAN` entries are truncated
 pub fn read_positions(
     storage: &dyn Storage,
     start_after: Option<Uint128>,
-    limit: Option<u32>,
+    limit: usize,
     order_by: Option<OrderBy>,
-) -> StdResult<Vec<Position>> {
-    let position_bucket: ReadonlyBucket<Position> = ReadonlyBucket::new(storage, PREFIX_POSITION);
+) -> StdResult<PositionScan> {
+    let (start, end, order_by) = calc_range(start_after, order_by);
 
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let (start, end, order_by) = match order_by {
-        Some(OrderBy::Asc) => (calc_range_start(start_after), None, OrderBy::Asc),
-        _ => (None, calc_range_end(start_after), OrderBy::Desc),
+    scan_positions(
+        positions().range(storage, start, end, order_by.into()),
+        limit,
+    )
+}
+
+pub fn read_positions_with_user_indexer(
+    storage: &dyn Storage,
+    position_owner: &CanonicalAddr,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<PositionScan> {
+    let (start, end, order_by) = calc_range(start_after, order_by);
+
+    let prefix = positions().idx.owner.prefix(position_owner.to_vec());
+    scan_positions(prefix.range(storage, start, end, order_by.into()), limit)
+}
+
+pub fn read_positions_with_asset_indexer(
+    storage: &dyn Storage,
+    asset_token: &CanonicalAddr,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<PositionScan> {
+    let (start, end, order_by) = calc_range(start_after, order_by);
+
+    let prefix = positions().idx.asset.prefix(asset_token.to_vec());
+    scan_positions(prefix.range(storage, start, end, order_by.into()), limit)
+}
+
+pub fn read_positions_with_referrer_indexer(
+    storage: &dyn Storage,
+    referrer: &CanonicalAddr,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<PositionScan> {
+    let (start, end, order_by) = calc_range(start_after, order_by);
+
+    let iter = REFERRED_POSITIONS
+        .prefix(referrer.as_slice())
+        .keys(storage, start, end, order_by.into())
+        .map(|idx| {
+            let idx = idx?;
+            Ok((idx, positions().load(storage, idx)?))
+        });
+    scan_positions(iter, limit)
+}
+
+// granularity of the collateral ratio index, 2^6 buckets per doubling of the ratio,
+// i.e. buckets are about 1.1% wide whatever the prices of the pair
+const COLLATERAL_RATIO_BUCKET_BITS: u32 = 6;
+
+/// Collateral ratio index bucket of a position: the collateral amount per unit of minted asset
+/// on a log scale. Amounts are compared instead of values, so within one asset and collateral
+/// pair a lower bucket means a lower collateral ratio at any price
+pub fn collateral_ratio_bucket(collateral_amount: Uint128, asset_amount: Uint128) -> u64 {
+    if asset_amount.is_zero() {
+        return u64::MAX;
+    }
+
+    log2_bucket(collateral_amount.u128(), asset_amount.u128())
+}
+
+/// bucket holding positions with exactly `collateral_per_asset` collateral per unit of asset
+pub fn collateral_ratio_bucket_of(collateral_per_asset: Decimal) -> u64 {
+    log2_bucket(
+        collateral_per_asset.atomics().u128(),
+        Decimal::one().atomics().u128(),
+    )
+}
+
+// floor(log2(numerator / denominator) * 2^COLLATERAL_RATIO_BUCKET_BITS), offset by 128
+// doublings so every ratio of two u128 maps to a bucket in [0, 256 << BITS)
+fn log2_bucket(numerator: u128, denominator: u128) -> u64 {
+    if numerator == 0 {
+        return 0;
+    }
+
+    // mantissa of the ratio in [1, 2) as a fixed point number with 64 fractional bits
+    let one = Uint256::one() << 64;
+    let mut exponent = (denominator.leading_zeros() as i32) - (numerator.leading_zeros() as i32);
+    let mut mantissa = if exponent >= 0 {
+        (Uint256::from(numerator) << 64) / (Uint256::from(denominator) << exponent as u32)
+    } else {
+        (Uint256::from(numerator) << (64 + exponent.unsigned_abs())) / Uint256::from(denominator)
+    };
+    if mantissa < one {
+        exponent -= 1;
+        mantissa <<= 1;
+    }
+
+    // every squaring of the mantissa yields the next fractional bit of the log
+    let mut bucket = (exponent + 128) as u64;
+    for _ in 0..COLLATERAL_RATIO_BUCKET_BITS {
+        mantissa = (mantissa * mantissa) >> 64;
+        bucket <<= 1;
+        if mantissa >= one << 1 {
+            bucket += 1;
+            mantissa >>= 1;
+        }
+    }
+    bucket
+}
+
+/// Entry of the collateral ratio index of a pair, `bucket:idx` as a page cursor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollateralRatioKey {
+    pub bucket: u64,
+    pub idx: u128,
+}
+
+impl fmt::Display for CollateralRatioKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.bucket, self.idx)
+    }
+}
+
+impl FromStr for CollateralRatioKey {
+    type Err = StdError;
+
+    fn from_str(s: &str) -> StdResult<Self> {
+        let invalid = || StdError::generic_err(format!("Invalid collateral ratio key: {}", s));
+        let (bucket, idx) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(CollateralRatioKey {
+            bucket: bucket.parse().map_err(|_| invalid())?,
+            idx: idx.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// positions matched by a capped scan of the collateral ratio index
+pub struct CollateralRatioScan {
+    pub positions: Vec<Position>,
+    /// last entry visited, matched or not; the next page starts after it
+    pub last_key: Option<CollateralRatioKey>,
+    /// the scan stopped at `MAX_POSITION_SCAN` visited entries with entries left to read
+    pub truncated: bool,
+}
+
+/// positions of the asset and collateral pair matching `filter`, lowest collateral ratio first
+/// unless the order is descending, scanning the entries after `start_after` in buckets up to
+/// and including `max_bucket`. At most `MAX_POSITION_SCAN` entries are visited, matched or not
+#[allow(clippy::too_many_arguments)]
+pub fn read_positions_with_collateral_ratio_indexer(
+    storage: &dyn Storage,
+    asset_info: &AssetInfoRaw,
+    collateral_info: &AssetInfoRaw,
+    start_after: Option<CollateralRatioKey>,
+    max_bucket: u64,
+    limit: usize,
+    order_by: Option<OrderBy>,
+    filter: impl Fn(&Position) -> bool,
+) -> StdResult<CollateralRatioScan> {
+    let prefix = positions().idx.collateral_ratio.sub_prefix((
+        asset_info.as_bytes().to_vec(),
+        collateral_info.as_bytes().to_vec(),
+    ));
+    let max = (max_bucket, u128::MAX);
+    let mut iter = match order_by {
+        Some(OrderBy::Desc) => {
+            // when resuming, the cursor is the tighter upper bound
+            let end = match start_after {
+                Some(key) if (key.bucket, key.idx) <= max => {
+                    Bound::exclusive((key.bucket, key.idx))
+                }
+                _ => Bound::inclusive(max),
+            };
+            prefix.range(storage, None, Some(end), Order::Descending)
+        }
+        _ => prefix.range(
+            storage,
+            start_after.map(|key| Bound::exclusive((key.bucket, key.idx))),
+            Some(Bound::inclusive(max)),
+            Order::Ascending,
+        ),
+    };
+
+    let mut scan = CollateralRatioScan {
+        positions: vec![],
+        last_key: None,
+        truncated: false,
     };
+    for _ in 0..MAX_POSITION_SCAN {
+        if scan.positions.len() >= limit {
+            return Ok(scan);
+        }
+        let (idx, position) = match iter.next() {
+            Some(item) => item?,
+            None => return Ok(scan),
+        };
+        scan.last_key = Some(CollateralRatioKey {
+            bucket: collateral_ratio_bucket(position.collateral.amount, position.asset.amount),
+            idx,
+        });
+        if filter(&position) {
+            scan.positions.push(position);
+        }
+    }
 
-    position_bucket
-        .range(start.as_deref(), end.as_deref(), order_by.into())
+    scan.truncated = scan.positions.len() < limit && iter.next().is_some();
+    Ok(scan)
+}
+
+/// positions closed at or before `closed_before`, oldest first
+pub fn read_closed_positions(
+    storage: &dyn Storage,
+    closed_before: u64,
+    limit: usize,
+) -> StdResult<Vec<Position>> {
+    positions()
+        .idx
+        .closed_at
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive((closed_before, u128::MAX))),
+            Order::Ascending,
+        )
         .take(limit)
         .map(|item| {
             let (_, v) = item?;
@@ -157,37 +678,674 @@ pub fn read_positions(
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<Uint128>) -> Option<Vec<u8>> {
-    start_after.map(|idx| {
-        let mut v = idx.u128().to_be_bytes().to_vec();
-        v.push(1);
-        v
-    })
+/// compact record kept for a pruned position
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArchivedPosition {
+    pub idx: Uint128,
+    pub owner: CanonicalAddr,
+    pub collateral: AssetInfoRaw,
+    pub asset: AssetInfoRaw,
+    pub closed_at: u64,
+}
+
+const ARCHIVED_POSITIONS: Map<u128, ArchivedPosition> = Map::new("archived_position");
+
+pub fn store_archived_position(
+    storage: &mut dyn Storage,
+    archived_position: &ArchivedPosition,
+) -> StdResult<()> {
+    ARCHIVED_POSITIONS.save(storage, archived_position.idx.u128(), archived_position)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_end(start_after: Option<Uint128>) -> Option<Vec<u8>> {
-    start_after.map(|idx| idx.u128().to_be_bytes().to_vec())
+pub fn read_archived_position(storage: &dyn Storage, idx: Uint128) -> StdResult<ArchivedPosition> {
+    ARCHIVED_POSITIONS.load(storage, idx.u128())
 }
 
-/// remove position with idx
-pub fn remove_position(storage: &mut dyn Storage, idx: Uint128) -> StdResult<()> {
-    let position: Position = read_position(storage, idx)?;
-    let mut position_bucket: Bucket<Position> = Bucket::new(storage, PREFIX_POSITION);
-    position_bucket.remove(&idx.u128().to_be_bytes());
+/// counterparty endpoints of the connected transfer channels, keyed by local channel id
+const IBC_CHANNELS: Map<&str, IbcEndpoint> = Map::new("ibc_channel");
+
+/// assets sent out over a channel and not yet returned, keyed by (channel id, asset token)
+const IBC_OUTSTANDING: Map<(&str, &[u8]), Uint128> = Map::new("ibc_outstanding");
 
-    // remove indexer
-    let mut position_indexer_by_user: Bucket<bool> =
-        Bucket::multilevel(storage, &[PREFIX_INDEX_BY_USER, position.owner.as_slice()]);
-    position_indexer_by_user.remove(&idx.u128().to_be_bytes());
+/// counterparty ports transfer channels can be opened with
+const IBC_COUNTERPARTY_PORTS: Map<&str, bool> = Map::new("ibc_counterparty_port");
+
+/// asset returning over a channel, read back when its mint fails
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcReceive {
+    pub channel_id: String,
+    pub asset_token: CanonicalAddr,
+    pub amount: Uint128,
+}
 
-    // remove indexer
-    let mut position_indexer_by_asset: Bucket<bool> = Bucket::multilevel(
+pub fn store_ibc_receive(storage: &mut dyn Storage, ibc_receive: &IbcReceive) -> StdResult<()> {
+    singleton(storage, KEY_IBC_RECEIVE).save(ibc_receive)
+}
+
+pub fn read_ibc_receive(storage: &dyn Storage) -> StdResult<IbcReceive> {
+    singleton_read(storage, KEY_IBC_RECEIVE).load()
+}
+
+pub fn store_ibc_counterparty_port(storage: &mut dyn Storage, port_id: &str) -> StdResult<()> {
+    IBC_COUNTERPARTY_PORTS.save(storage, port_id, &true)
+}
+
+pub fn remove_ibc_counterparty_port(storage: &mut dyn Storage, port_id: &str) {
+    IBC_COUNTERPARTY_PORTS.remove(storage, port_id)
+}
+
+pub fn is_ibc_counterparty_port(storage: &dyn Storage, port_id: &str) -> bool {
+    IBC_COUNTERPARTY_PORTS.has(storage, port_id)
+}
+
+pub fn store_ibc_channel(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    counterparty: &IbcEndpoint,
+) -> StdResult<()> {
+    IBC_CHANNELS.save(storage, channel_id, counterparty)
+}
+
+pub fn read_ibc_channel(storage: &dyn Storage, channel_id: &str) -> StdResult<IbcEndpoint> {
+    IBC_CHANNELS
+        .load(storage, channel_id)
+        .map_err(|_| StdError::generic_err("Unknown transfer channel"))
+}
+
+/// outstanding amount of the asset summed over every channel
+pub fn read_total_ibc_outstanding(
+    storage: &dyn Storage,
+    asset_token: &CanonicalAddr,
+) -> StdResult<Uint128> {
+    let channel_ids = IBC_CHANNELS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    let mut total = Uint128::zero();
+    for channel_id in channel_ids.iter() {
+        total = total.checked_add(read_ibc_outstanding(storage, channel_id, asset_token)?)?;
+    }
+    Ok(total)
+}
+
+pub fn read_ibc_outstanding(
+    storage: &dyn Storage,
+    channel_id: &str,
+    asset_token: &CanonicalAddr,
+) -> StdResult<Uint128> {
+    Ok(IBC_OUTSTANDING
+        .may_load(storage, (channel_id, asset_token.as_slice()))?
+        .unwrap_or_default())
+}
+
+pub fn increase_ibc_outstanding(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    asset_token: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let outstanding = read_ibc_outstanding(storage, channel_id, asset_token)?;
+    IBC_OUTSTANDING.save(
         storage,
-        &[PREFIX_INDEX_BY_ASSET, position.asset.info.as_bytes()],
-    );
-    position_indexer_by_asset.remove(&idx.u128().to_be_bytes());
+        (channel_id, asset_token.as_slice()),
+        &outstanding.checked_add(amount)?,
+    )
+}
+
+/// fails without writing if more is returned over the channel than was sent out over it
+pub fn decrease_ibc_outstanding(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    asset_token: &CanonicalAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let outstanding = read_ibc_outstanding(storage, channel_id, asset_token)?;
+    let outstanding = outstanding.checked_sub(amount).map_err(|_| {
+        StdError::generic_err("Returned amount exceeds the amount sent over the channel")
+    })?;
+    IBC_OUTSTANDING.save(storage, (channel_id, asset_token.as_slice()), &outstanding)
+}
+
+/// protocol and mint fees charged to a position so far, one entry per fee asset
+const POSITION_FEES: Map<u128, Vec<AssetRaw>> = Map::new("position_fees");
+
+pub fn add_position_fee(
+    storage: &mut dyn Storage,
+    position_idx: Uint128,
+    fee: AssetRaw,
+) -> StdResult<()> {
+    let mut fees = read_position_fees(storage, position_idx)?;
+    match fees.iter_mut().find(|paid| paid.info.equal(&fee.info)) {
+        Some(paid) => paid.amount = paid.amount.checked_add(fee.amount)?,
+        None => fees.push(fee),
+    }
+    POSITION_FEES.save(storage, position_idx.u128(), &fees)
+}
+
+pub fn read_position_fees(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+) -> StdResult<Vec<AssetRaw>> {
+    Ok(POSITION_FEES
+        .may_load(storage, position_idx.u128())?
+        .unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenPosition {
+    pub reason: String,
+    pub frozen_at: u64,
+}
+
+const FROZEN_POSITIONS: Map<u128, FrozenPosition> = Map::new("frozen_position");
+
+pub fn store_frozen_position(
+    storage: &mut dyn Storage,
+    position_idx: Uint128,
+    frozen_position: &FrozenPosition,
+) -> StdResult<()> {
+    FROZEN_POSITIONS.save(storage, position_idx.u128(), frozen_position)
+}
+
+pub fn read_frozen_position(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+) -> StdResult<Option<FrozenPosition>> {
+    FROZEN_POSITIONS.may_load(storage, position_idx.u128())
+}
+
+pub fn remove_frozen_position(storage: &mut dyn Storage, position_idx: Uint128) {
+    FROZEN_POSITIONS.remove(storage, position_idx.u128())
+}
+
+/// the order defaults to ascending
+pub fn read_frozen_positions(
+    storage: &dyn Storage,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<(Uint128, FrozenPosition)>> {
+    let (start, end, order_by) = calc_range(start_after, Some(order_by.unwrap_or(OrderBy::Asc)));
+
+    FROZEN_POSITIONS
+        .range(storage, start, end, order_by.into())
+        .take(limit)
+        .map(|item| {
+            let (idx, frozen_position) = item?;
+            Ok((Uint128::from(idx), frozen_position))
+        })
+        .collect()
+}
+
+/// Owner, guardian or governance action, recorded once it succeeded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminAction {
+    pub height: u64,
+    pub time: u64,
+    pub actor: CanonicalAddr,
+    pub action: String,
+    /// hex encoded sha256 of the JSON message that performed the action
+    pub payload_hash: String,
+}
+
+/// append-only log of admin actions, keyed by sequence number
+const ADMIN_ACTIONS: Map<u128, AdminAction> = Map::new("admin_actions");
+const ADMIN_ACTION_COUNT: Item<Uint128> = Item::new("admin_action_count");
+
+/// appends the action to the log, returning its sequence number
+pub fn append_admin_action(
+    storage: &mut dyn Storage,
+    admin_action: &AdminAction,
+) -> StdResult<Uint128> {
+    let id = ADMIN_ACTION_COUNT.may_load(storage)?.unwrap_or_default();
+    ADMIN_ACTIONS.save(storage, id.u128(), admin_action)?;
+    ADMIN_ACTION_COUNT.save(storage, &(id + Uint128::from(1u128)))?;
+    Ok(id)
+}
+
+/// the order defaults to ascending
+pub fn read_admin_actions(
+    storage: &dyn Storage,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<(Uint128, AdminAction)>> {
+    let (start, end, order_by) = calc_range(start_after, Some(order_by.unwrap_or(OrderBy::Asc)));
+
+    ADMIN_ACTIONS
+        .range(storage, start, end, order_by.into())
+        .take(limit)
+        .map(|item| {
+            let (id, admin_action) = item?;
+            Ok((Uint128::from(id), admin_action))
+        })
+        .collect()
+}
+
+/// State-changing action on a position, with the position as the action left it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JournalEntry {
+    pub height: u64,
+    pub time: u64,
+    pub action: String,
+    pub owner: CanonicalAddr,
+    /// collateral the action added to or took out of the position
+    pub collateral_amount: Uint128,
+    /// asset the action minted or burned against the position
+    pub asset_amount: Uint128,
+    pub asset_price: Option<Decimal>,
+    pub collateral_price: Option<Decimal>,
+    pub fees: Vec<AssetRaw>,
+    pub collateral_after: Uint128,
+    pub asset_after: Uint128,
+}
+
+/// assets of a journaled position and the number of entries in its journal
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JournalHeader {
+    pub collateral: AssetInfoRaw,
+    pub asset: AssetInfoRaw,
+    pub length: Uint128,
+}
+
+/// append-only journal of every position, keyed by position idx and sequence number;
+/// it outlives the position, so removed and pruned positions can still be replayed
+const POSITION_JOURNALS: Map<(u128, u128), JournalEntry> = Map::new("position_journals");
+const POSITION_JOURNAL_HEADERS: Map<u128, JournalHeader> = Map::new("position_journal_headers");
+
+pub fn append_journal_entry(
+    storage: &mut dyn Storage,
+    position: &Position,
+    entry: &JournalEntry,
+) -> StdResult<()> {
+    let mut header = POSITION_JOURNAL_HEADERS
+        .may_load(storage, position.idx.u128())?
+        .unwrap_or_else(|| JournalHeader {
+            collateral: position.collateral.info.clone(),
+            asset: position.asset.info.clone(),
+            length: Uint128::zero(),
+        });
+    POSITION_JOURNALS.save(storage, (position.idx.u128(), header.length.u128()), entry)?;
+    header.length += Uint128::from(1u128);
+    POSITION_JOURNAL_HEADERS.save(storage, position.idx.u128(), &header)
+}
+
+pub fn read_journal_header(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+) -> StdResult<JournalHeader> {
+    POSITION_JOURNAL_HEADERS
+        .may_load(storage, position_idx.u128())?
+        .ok_or_else(|| StdError::generic_err("Position has no journal"))
+}
+
+/// the order defaults to ascending
+pub fn read_journal_entries(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<(Uint128, JournalEntry)>> {
+    let (start, end, order_by) = calc_range(start_after, Some(order_by.unwrap_or(OrderBy::Asc)));
+
+    POSITION_JOURNALS
+        .prefix(position_idx.u128())
+        .range(storage, start, end, order_by.into())
+        .take(limit)
+        .map(|item| {
+            let (seq, entry) = item?;
+            Ok((Uint128::from(seq), entry))
+        })
+        .collect()
+}
+
+/// collateral ratio below which a position shows up in the triggered alerts
+const POSITION_ALERTS: Map<u128, Decimal> = Map::new("position_alert");
+
+pub fn store_position_alert(
+    storage: &mut dyn Storage,
+    position_idx: Uint128,
+    ratio: Decimal,
+) -> StdResult<()> {
+    POSITION_ALERTS.save(storage, position_idx.u128(), &ratio)
+}
+
+pub fn remove_position_alert(storage: &mut dyn Storage, position_idx: Uint128) {
+    POSITION_ALERTS.remove(storage, position_idx.u128())
+}
+
+// cap on the alerts a single read visits, each visit reads a position and possibly prices
+const MAX_ALERT_SCAN: usize = 30;
+
+/// alerts mapped to an entry by a capped scan
+pub struct AlertScan<T> {
+    pub alerts: Vec<T>,
+    /// position of the last alert visited, mapped or not; the next page starts after it
+    pub last_idx: Option<Uint128>,
+    /// the scan stopped at `MAX_ALERT_SCAN` visited alerts with alerts left to read
+    pub truncated: bool,
+}
+
+/// alerts after `start_after` in position order (ascending by default), until `limit` of
+/// them are mapped to an entry by `filter_map` or `MAX_ALERT_SCAN` of them are visited
+pub fn read_position_alerts<T, F>(
+    storage: &dyn Storage,
+    start_after: Option<Uint128>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+    mut filter_map: F,
+) -> StdResult<AlertScan<T>>
+where
+    F: FnMut(Uint128, Decimal) -> StdResult<Option<T>>,
+{
+    let (start, end, order_by) = calc_range(start_after, Some(order_by.unwrap_or(OrderBy::Asc)));
+    let mut iter = POSITION_ALERTS.range(storage, start, end, order_by.into());
+
+    let mut scan = AlertScan {
+        alerts: vec![],
+        last_idx: None,
+        truncated: false,
+    };
+    for _ in 0..MAX_ALERT_SCAN {
+        if scan.alerts.len() >= limit {
+            return Ok(scan);
+        }
+        let (idx, ratio) = match iter.next() {
+            Some(item) => item?,
+            None => return Ok(scan),
+        };
+        scan.last_idx = Some(Uint128::from(idx));
+        if let Some(alert) = filter_map(Uint128::from(idx), ratio)? {
+            scan.alerts.push(alert);
+        }
+    }
+
+    scan.truncated = scan.alerts.len() < limit && iter.next().is_some();
+    Ok(scan)
+}
+
+/// contracts allowed to open positions while the integrator allow-list is enabled
+const INTEGRATORS: Map<&[u8], bool> = Map::new("integrator");
+const INTEGRATOR_ALLOWLIST_ENABLED: Item<bool> = Item::new("integrator_allowlist_enabled");
+
+pub fn store_integrator(storage: &mut dyn Storage, integrator: &CanonicalAddr) -> StdResult<()> {
+    INTEGRATORS.save(storage, integrator.as_slice(), &true)
+}
+
+pub fn remove_integrator(storage: &mut dyn Storage, integrator: &CanonicalAddr) {
+    INTEGRATORS.remove(storage, integrator.as_slice())
+}
+
+pub fn is_integrator(storage: &dyn Storage, integrator: &CanonicalAddr) -> bool {
+    INTEGRATORS.has(storage, integrator.as_slice())
+}
+
+/// the order defaults to ascending
+pub fn read_integrators(
+    storage: &dyn Storage,
+    start_after: Option<CanonicalAddr>,
+    limit: usize,
+    order_by: Option<OrderBy>,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let start_after = start_after
+        .as_ref()
+        .map(|integrator| Bound::exclusive(integrator.as_slice()));
+    let (start, end, order_by) = match order_by {
+        Some(OrderBy::Desc) => (None, start_after, OrderBy::Desc),
+        _ => (start_after, None, OrderBy::Asc),
+    };
+
+    INTEGRATORS
+        .keys(storage, start, end, order_by.into())
+        .take(limit)
+        .map(|key| Ok(CanonicalAddr::from(key?)))
+        .collect()
+}
+
+pub fn store_integrator_allowlist_enabled(
+    storage: &mut dyn Storage,
+    enabled: bool,
+) -> StdResult<()> {
+    INTEGRATOR_ALLOWLIST_ENABLED.save(storage, &enabled)
+}
+
+pub fn read_integrator_allowlist_enabled(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(INTEGRATOR_ALLOWLIST_ENABLED
+        .may_load(storage)?
+        .unwrap_or(false))
+}
+
+/// Secondary key allowed to deposit to and burn against a single position on behalf of
+/// its owner, within daily caps denominated in the position's collateral and asset
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SessionKey {
+    /// owner that granted the key; the grant lapses once the position changes hands
+    pub granter: CanonicalAddr,
+    pub expires_at: u64,
+    pub daily_deposit_cap: Uint128,
+    pub daily_burn_cap: Uint128,
+    /// day (block time / 86400) the spent amounts below belong to
+    pub day: u64,
+    pub deposited: Uint128,
+    pub burned: Uint128,
+}
+
+/// session keys a position can have at once
+pub const MAX_SESSION_KEYS: usize = 10;
+
+/// session keys keyed by (position idx, key)
+const SESSION_KEYS: Map<(u128, &[u8]), SessionKey> = Map::new("session_key");
+
+pub fn store_session_key(
+    storage: &mut dyn Storage,
+    position_idx: Uint128,
+    key: &CanonicalAddr,
+    session_key: &SessionKey,
+) -> StdResult<()> {
+    SESSION_KEYS.save(storage, (position_idx.u128(), key.as_slice()), session_key)
+}
+
+pub fn read_session_key(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+    key: &CanonicalAddr,
+) -> StdResult<Option<SessionKey>> {
+    SESSION_KEYS.may_load(storage, (position_idx.u128(), key.as_slice()))
+}
+
+pub fn remove_session_key(storage: &mut dyn Storage, position_idx: Uint128, key: &CanonicalAddr) {
+    SESSION_KEYS.remove(storage, (position_idx.u128(), key.as_slice()))
+}
+
+pub fn read_session_keys(
+    storage: &dyn Storage,
+    position_idx: Uint128,
+) -> StdResult<Vec<(CanonicalAddr, SessionKey)>> {
+    SESSION_KEYS
+        .prefix(position_idx.u128())
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, session_key) = item?;
+            Ok((CanonicalAddr::from(key), session_key))
+        })
+        .collect()
+}
+
+// start_after is exclusive in both directions, the order defaults to descending
+fn calc_range<'a>(
+    start_after: Option<Uint128>,
+    order_by: Option<OrderBy>,
+) -> (Option<Bound<'a, u128>>, Option<Bound<'a, u128>>, OrderBy) {
+    let start_after = start_after.map(|idx| Bound::exclusive(idx.u128()));
+    match order_by {
+        Some(OrderBy::Asc) => (start_after, None, OrderBy::Asc),
+        _ => (None, start_after, OrderBy::Desc),
+    }
+}
 
+/// remove position with idx along with its alert, fee history, freeze record and
+/// session keys; its journal is kept
+pub fn remove_position(storage: &mut dyn Storage, idx: Uint128) -> StdResult<()> {
+    POSITION_ALERTS.remove(storage, idx.u128());
+    POSITION_FEES.remove(storage, idx.u128());
+    FROZEN_POSITIONS.remove(storage, idx.u128());
+    // bounded per position by MAX_SESSION_KEYS
+    let session_keys = SESSION_KEYS
+        .prefix(idx.u128())
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+    for key in session_keys.iter() {
+        SESSION_KEYS.remove(storage, (idx.u128(), key.as_slice()));
+    }
+    let old = positions().may_load(storage, idx.u128())?;
+    update_asset_totals(storage, idx, old.as_ref(), None)?;
+    if let Some(referrer) = old.as_ref().and_then(|position| position.referrer.as_ref()) {
+        REFERRED_POSITIONS.remove(storage, (referrer.as_slice(), idx.u128()));
+    }
+    positions().replace(storage, idx.u128(), None, old.as_ref())
+}
+
+/// Running totals of the positions of an asset, kept in step with every position write
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct AssetTotals {
+    /// asset owed by the positions
+    pub total_minted: Uint128,
+    /// collateral locked in the positions, one entry per collateral type
+    pub total_collateral: Vec<AssetRaw>,
+}
+
+const ASSET_TOTALS: Map<&[u8], AssetTotals> = Map::new("asset_totals");
+/// set once the totals account for every position; unset on contracts upgraded from a
+/// version without totals until the backfill completes
+const ASSET_TOTALS_COMPLETE: Item<bool> = Item::new("asset_totals_complete");
+
+pub fn read_asset_totals(
+    storage: &dyn Storage,
+    asset_token: &CanonicalAddr,
+) -> StdResult<AssetTotals> {
+    Ok(ASSET_TOTALS
+        .may_load(storage, asset_token.as_slice())?
+        .unwrap_or_default())
+}
+
+pub fn read_asset_totals_complete(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(ASSET_TOTALS_COMPLETE.may_load(storage)?.unwrap_or(false))
+}
+
+pub fn store_asset_totals_complete(storage: &mut dyn Storage, complete: bool) -> StdResult<()> {
+    ASSET_TOTALS_COMPLETE.save(storage, &complete)
+}
+
+/// drops the totals of every asset, ahead of recounting them
+pub fn clear_asset_totals(storage: &mut dyn Storage) -> StdResult<()> {
+    let keys = ASSET_TOTALS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+    for key in keys {
+        ASSET_TOTALS.remove(storage, &key);
+    }
+    ASSET_TOTALS_COMPLETE.save(storage, &false)
+}
+
+/// Adds the debt and collateral of the position to the totals of its asset,
+/// or takes them out when `add` is false
+pub fn apply_position_totals(
+    storage: &mut dyn Storage,
+    position: &Position,
+    add: bool,
+) -> StdResult<()> {
+    if position.asset.amount.is_zero() && position.collateral.amount.is_zero() {
+        return Ok(());
+    }
+
+    let key = position.asset.info.as_bytes();
+    let mut totals = ASSET_TOTALS.may_load(storage, key)?.unwrap_or_default();
+    let collateral_idx = match totals
+        .total_collateral
+        .iter()
+        .position(|collateral| collateral.info.equal(&position.collateral.info))
+    {
+        Some(collateral_idx) => collateral_idx,
+        None => {
+            totals.total_collateral.push(AssetRaw {
+                info: position.collateral.info.clone(),
+                amount: Uint128::zero(),
+            });
+            totals.total_collateral.len() - 1
+        }
+    };
+    let total_collateral = &mut totals.total_collateral[collateral_idx].amount;
+
+    if add {
+        totals.total_minted = totals.total_minted.checked_add(position.asset.amount)?;
+        *total_collateral = total_collateral.checked_add(position.collateral.amount)?;
+    } else {
+        totals.total_minted = totals.total_minted.checked_sub(position.asset.amount)?;
+        *total_collateral = total_collateral.checked_sub(position.collateral.amount)?;
+    }
+
+    ASSET_TOTALS.save(storage, key, &totals)
+}
+
+/// collateral of the type locked in the positions of every asset
+pub fn read_total_collateral(
+    storage: &dyn Storage,
+    collateral: &AssetInfoRaw,
+) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for asset_config in read_asset_configs(storage)? {
+        for total_collateral in read_asset_totals(storage, &asset_config.token)?.total_collateral {
+            if total_collateral.info.equal(collateral) {
+                total = total.checked_add(total_collateral.amount)?;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// maximum amount of a collateral locked across all positions, keyed by collateral
+const COLLATERAL_CAPS: Map<&[u8], Uint128> = Map::new("collateral_caps");
+
+pub fn store_collateral_cap(
+    storage: &mut dyn Storage,
+    collateral: &AssetInfoRaw,
+    cap: Uint128,
+) -> StdResult<()> {
+    COLLATERAL_CAPS.save(storage, collateral.as_bytes(), &cap)
+}
+
+pub fn remove_collateral_cap(storage: &mut dyn Storage, collateral: &AssetInfoRaw) {
+    COLLATERAL_CAPS.remove(storage, collateral.as_bytes())
+}
+
+pub fn read_collateral_cap(
+    storage: &dyn Storage,
+    collateral: &AssetInfoRaw,
+) -> StdResult<Option<Uint128>> {
+    COLLATERAL_CAPS.may_load(storage, collateral.as_bytes())
+}
+
+// positions a running backfill has not reached yet are left to it
+fn update_asset_totals(
+    storage: &mut dyn Storage,
+    idx: Uint128,
+    old: Option<&Position>,
+    new: Option<&Position>,
+) -> StdResult<()> {
+    let counted = match backfilled_until(storage)? {
+        Some(last_position_idx) => last_position_idx.map_or(false, |last| idx.u128() <= last),
+        None => read_asset_totals_complete(storage)?,
+    };
+    if !counted {
+        return Ok(());
+    }
+
+    if let Some(old) = old {
+        apply_position_totals(storage, old, false)?;
+    }
+    if let Some(new) = new {
+        apply_position_totals(storage, new, true)?;
+    }
     Ok(())
 }
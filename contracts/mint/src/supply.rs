@@ -0,0 +1,83 @@
+use cosmwasm_std::{attr, Deps, DepsMut, MessageInfo, Response, StdError, StdResult};
+use melange_protocol::{asset::query_supply, attributes::ACTION, mint::SupplyCheckResponse};
+
+use crate::{
+    asserts::assert_owner_or_guardian,
+    state::{
+        read_asset_config, read_asset_totals, read_asset_totals_complete, read_config,
+        read_total_ibc_outstanding, store_asset_config, AssetConfig, Config,
+    },
+};
+
+/// Compares the supply of the asset, on this chain and sent over IBC, with the debt of
+/// all positions in it
+pub fn query_supply_check(deps: Deps, asset_token: String) -> StdResult<SupplyCheckResponse> {
+    let asset_token_addr = deps.api.addr_validate(asset_token.as_str())?;
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    // fail for unregistered assets rather than report an empty debt
+    read_asset_config(deps.storage, &asset_token_raw)?;
+
+    let total_supply = query_supply(&deps.querier, asset_token_addr)?;
+    let ibc_outstanding = read_total_ibc_outstanding(deps.storage, &asset_token_raw)?;
+    let total_debt = read_asset_totals(deps.storage, &asset_token_raw)?.total_minted;
+
+    let circulating = total_supply.checked_add(ibc_outstanding)?;
+    Ok(SupplyCheckResponse {
+        asset_token,
+        total_supply,
+        ibc_outstanding,
+        total_debt,
+        surplus: circulating.saturating_sub(total_debt),
+        deficit: total_debt.saturating_sub(circulating),
+        totals_complete: read_asset_totals_complete(deps.storage)?,
+    })
+}
+
+pub fn check_supply(deps: DepsMut, info: MessageInfo, asset_token: String) -> StdResult<Response> {
+    assert_owner_or_guardian(deps.as_ref(), &info)?;
+
+    let supply_check = query_supply_check(deps.as_ref(), asset_token.clone())?;
+    if !supply_check.totals_complete {
+        return Err(StdError::generic_err(
+            "Asset totals are still being backfilled",
+        ));
+    }
+
+    // a deficit only means tokens left circulation without repaying debt,
+    // unbacked supply is what minting must not add to
+    let violated = !supply_check.surplus.is_zero();
+    if violated {
+        let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+        let mut asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+        asset_config.mint_paused = true;
+        store_asset_config(deps.storage, &asset_token_raw, &asset_config)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "check_supply"),
+        attr("asset_token", asset_token),
+        attr("surplus", supply_check.surplus.to_string()),
+        attr("deficit", supply_check.deficit.to_string()),
+        attr("mint_paused", violated.to_string()),
+    ]))
+}
+
+pub fn unpause_mint(deps: DepsMut, info: MessageInfo, asset_token: String) -> StdResult<Response> {
+    let config: Config = read_config(deps.storage)?;
+    if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let asset_token_raw = deps.api.addr_canonicalize(asset_token.as_str())?;
+    let mut asset_config: AssetConfig = read_asset_config(deps.storage, &asset_token_raw)?;
+    if !asset_config.mint_paused {
+        return Err(StdError::generic_err("Minting the asset is not paused"));
+    }
+    asset_config.mint_paused = false;
+    store_asset_config(deps.storage, &asset_token_raw, &asset_config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr(ACTION, "unpause_mint"),
+        attr("asset_token", asset_token),
+    ]))
+}
@@ -0,0 +1,102 @@
+//! Attribute keys emitted by the mint contract.
+//!
+//! The keys below are part of the contract interface: they are only ever added to,
+//! never renamed or removed. Amounts are always split into an `*_amount` key holding the
+//! integer amount and a `*_denom` key holding the native denom or the cw20 contract address.
+//!
+//! Every position action emits `action`, `position_idx`, `owner`, the oracle `asset_price`
+//! and `collateral_price` the action was executed at and, while any asset is minted against
+//! the position, the post-action `collateral_ratio`. Per action:
+//!
+//! - `open_position`: `collateral_amount`, `collateral_denom`, `asset_amount`, `asset_denom`,
+//!   optionally `referrer`
+//! - `deposit`, `withdraw`: `amount`, `denom` of the collateral moved
+//! - `open_position`, `mint`, `leverage_round` with an asset mint fee: `mint_fee_amount`,
+//!   `mint_fee_denom` of the asset minted to the collector out of `asset_amount`/`amount`
+//! - `mint`, `burn`: `amount`, `denom` of the asset moved; `burn` adds `protocol_fee_amount`,
+//!   `protocol_fee_denom`, optionally `refund_amount`, `refund_denom` for delisted assets and
+//!   `referrer`, `referral_fee_amount`, `referral_fee_denom` for referred positions
+//! - `transfer_position`: `owner` is the new owner, the previous one is in `previous_owner`
+//! - `set_alert`: the new `alert_ratio`, absent when the alert is removed
+//! - `set_position_label`: the new `label`, empty when cleared
+//! - `authorize_session_key`: `session_key` and its `expires_at`
+//! - `revoke_session_key`: `session_key`
+//! - `leverage_round`: `amount`, `denom` of the asset minted and sold in the round
+//! - `leverage`: emitted once the loop ends, with the reached `leverage` and `iterations`
+//! - `deleverage_round`: `amount`, `denom` of the collateral withdrawn and sold in the round
+//! - `deleverage_burn`: `amount`, `denom` of the asset burned, `protocol_fee_amount`,
+//!   `protocol_fee_denom`, optionally `refund_amount`, `refund_denom` of bought asset
+//!   exceeding the debt
+//! - `deleverage`: emitted once the loop ends, with the number of `iterations`
+//! - `close_with_swap_sell`: `amount`, `denom` of the collateral sold
+//! - `close_with_swap`: `amount`, `denom` of the asset burned, `protocol_fee_amount`,
+//!   `protocol_fee_denom`, `collateral_amount`, `collateral_denom` returned to the owner,
+//!   optionally `refund_amount`, `refund_denom` of bought asset exceeding the debt
+//!
+//! Flash mints are not bound to a position and emit `action`, `borrower`, `amount` and `denom`
+//! of the minted asset; `flash_mint_repay` adds `flash_mint_fee_amount`, `flash_mint_fee_denom`.
+//!
+//! Position freezes emit `action`, `position_idx` and the freezing owner or guardian:
+//! `freeze_position` with the `reason` and `frozen_by`, `unfreeze_position` with `unfrozen_by`.
+//!
+//! IBC transfers emit `action`, `sender`, `receiver`, `channel`, `amount` and `denom` of the
+//! asset: `ibc_transfer` when sent, `ibc_receive` when minted back from the remote chain,
+//! `ibc_transfer_error` and `ibc_transfer_timeout` when refunded to the sender, the former
+//! with the remote `error`
+//!
+//! `prune` emits the `pruned_count` of closed positions archived, which the health check
+//! also reports in its `heartbeat` event. Storage migrations emit `action` (`migrate`,
+//! `backfill_totals` or `continue_migration`), the `migrated_count` of the batch and
+//! `migration_complete`
+
+use cosmwasm_std::{attr, Attribute};
+
+use crate::asset::Asset;
+
+pub const ACTION: &str = "action";
+pub const POSITION_IDX: &str = "position_idx";
+pub const OWNER: &str = "owner";
+pub const PREVIOUS_OWNER: &str = "previous_owner";
+pub const REFERRER: &str = "referrer";
+pub const COLLATERAL_RATIO: &str = "collateral_ratio";
+pub const ASSET_PRICE: &str = "asset_price";
+pub const COLLATERAL_PRICE: &str = "collateral_price";
+
+pub const AMOUNT: &str = "amount";
+pub const DENOM: &str = "denom";
+
+pub const COLLATERAL: &str = "collateral";
+pub const ASSET: &str = "asset";
+pub const PROTOCOL_FEE: &str = "protocol_fee";
+pub const MINT_FEE: &str = "mint_fee";
+pub const REFUND: &str = "refund";
+pub const REFERRAL_FEE: &str = "referral_fee";
+pub const FLASH_MINT_FEE: &str = "flash_mint_fee";
+
+pub const BORROWER: &str = "borrower";
+pub const LEVERAGE: &str = "leverage";
+pub const ITERATIONS: &str = "iterations";
+
+pub const SENDER: &str = "sender";
+pub const RECEIVER: &str = "receiver";
+pub const CHANNEL: &str = "channel";
+
+pub const SESSION_KEY: &str = "session_key";
+
+pub const PRUNED_COUNT: &str = "pruned_count";
+pub const MIGRATED_COUNT: &str = "migrated_count";
+pub const MIGRATION_COMPLETE: &str = "migration_complete";
+
+/// `amount` and `denom` of the asset, or `{prefix}_amount` and `{prefix}_denom` if prefixed
+pub fn asset_attributes(prefix: Option<&str>, asset: &Asset) -> Vec<Attribute> {
+    match prefix {
+        Some(prefix) => vec![
+            attr(format!("{}_{}", prefix, AMOUNT), asset.amount.to_string()),
+            attr(format!("{}_{}", prefix, DENOM), asset.info.to_string()),
+        ],
+        None => vec![
+            attr(AMOUNT, asset.amount.to_string()),
+            attr(DENOM, asset.info.to_string()),
+        ],
+    }
+}
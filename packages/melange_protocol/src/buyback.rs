@@ -0,0 +1,58 @@
+//! Interface of the contract buying MEL back with the base_denom fees it receives from the
+//! collector and burning it. Only its messages live here, the contract itself is not part
+//! of this tree yet
+use cosmwasm_std::{Decimal, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub owner: String,
+    pub mel_token: String,
+    pub base_denom: String,
+    /// dex contract the MEL/base_denom market is registered on
+    pub dex_contract: String,
+    pub max_spread: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Update config; only owner is allowed to execute it
+    UpdateConfig {
+        owner: Option<String>,
+        dex_contract: Option<String>,
+        max_spread: Option<Decimal>,
+    },
+    /// Simulate and place a market buy of MEL with the base_denom balance
+    /// received from the collector; reverts if the simulated spread exceeds max_spread
+    Buyback {},
+    /// Burn the MEL balance held by the contract
+    Burn {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    State {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub mel_token: String,
+    pub base_denom: String,
+    pub dex_contract: String,
+    pub max_spread: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    /// cumulative base_denom spent on buybacks
+    pub total_spent: Uint128,
+    /// cumulative MEL bought
+    pub total_bought: Uint128,
+    /// cumulative MEL burned
+    pub total_burned: Uint128,
+}
@@ -3,6 +3,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use crate::asset::AssetInfo;
+use crate::common::{PageRequest, PageResponse};
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -25,6 +26,19 @@ pub enum ExecuteMsg {
         price_source: SourceType,
         multiplier: Decimal,
     },
+    /// Registers a bridged token as collateral. Its effective multiplier is `multiplier`
+    /// times the haircut of the bridge it came through
+    RegisterBridgedCollateralAsset {
+        asset: AssetInfo,
+        price_source: SourceType,
+        multiplier: Decimal,
+        provenance: Provenance,
+    },
+    /// Sets the haircut applied to every collateral bridged through `bridge`, must be <= 1
+    UpdateBridgeMultiplier {
+        bridge: Bridge,
+        multiplier: Decimal,
+    },
     RevokeCollateralAsset {
         asset: AssetInfo,
     },
@@ -50,6 +64,28 @@ pub enum QueryMsg {
         asset: String,
     },
     CollateralAssetInfos {},
+    /// Registered collaterals matching every set field of the filter, ordered by asset
+    CollateralAssets {
+        filter: Option<CollateralFilter>,
+        page: Option<PageRequest>,
+    },
+    /// Where the collateral comes from; native collaterals have no provenance
+    CollateralProvenance {
+        asset: String,
+    },
+    BridgeMultipliers {},
+    /// Prices of several assets in one query, in the requested order.
+    /// Assets that are not registered collaterals are priced by the oracle with a multiplier of one
+    Prices {
+        assets: Vec<AssetInfo>,
+        timeframe: Option<u64>,
+    },
+    /// Price, multiplier and validity of several assets in one query, in the requested order.
+    /// Unlike `Prices` an asset whose source cannot price it does not fail the query,
+    /// it is reported as suspended
+    CollateralInfos {
+        assets: Vec<AssetInfo>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -68,6 +104,11 @@ pub struct CollateralPriceResponse {
     pub is_revoked: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralPricesResponse {
+    pub prices: Vec<CollateralPriceResponse>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CollateralInfoResponse {
     pub asset: String,
@@ -81,6 +122,61 @@ pub struct CollateralInfosResponse {
     pub collaterals: Vec<CollateralInfoResponse>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct CollateralFilter {
+    pub is_revoked: Option<bool>,
+    /// Suspended collaterals are the ones their price source cannot currently price
+    pub is_suspended: Option<bool>,
+    /// `SourceType` name, e.g. `native` or `interchain_query`
+    pub source_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralAssetsResponse {
+    pub collaterals: Vec<CollateralInfoResponse>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralStatusResponse {
+    pub asset: String,
+    /// Zero while suspended
+    pub rate: Decimal,
+    pub last_updated: u64,
+    /// Seconds since `last_updated` at the queried block
+    pub age: u64,
+    pub multiplier: Decimal,
+    pub is_revoked: bool,
+    /// The price source could not price the asset, e.g. its interchain query has no result yet
+    pub is_suspended: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralStatusesResponse {
+    pub collaterals: Vec<CollateralStatusResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralProvenanceResponse {
+    pub asset: String,
+    pub provenance: Option<Provenance>,
+    /// Haircut of the bridge, one for native collaterals
+    pub bridge_multiplier: Decimal,
+    /// Registered multiplier times the bridge haircut
+    pub effective_multiplier: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BridgeMultiplierResponse {
+    pub bridge: Bridge,
+    pub multiplier: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BridgeMultipliersResponse {
+    pub bridges: Vec<BridgeMultiplierResponse>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {
     pub oracle_addr: String,
@@ -95,6 +191,12 @@ pub enum SourceType {
     Native {
         native_denom: String,
     },
+    /// Price living on another chain, read from its oracle or pool state through the
+    /// interchain query `query_id` of the `icq_contract` relayer, which verifies the proofs
+    InterchainQuery {
+        icq_contract: String,
+        query_id: u64,
+    },
 }
 
 impl fmt::Display for SourceType {
@@ -102,6 +204,33 @@ impl fmt::Display for SourceType {
         match *self {
             SourceType::FixedPrice { .. } => write!(f, "fixed_price"),
             SourceType::Native { .. } => write!(f, "native"),
+            SourceType::InterchainQuery { .. } => write!(f, "interchain_query"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Bridge {
+    Axelar,
+    Wormhole,
+}
+
+impl fmt::Display for Bridge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Bridge::Axelar => write!(f, "axelar"),
+            Bridge::Wormhole => write!(f, "wormhole"),
         }
     }
 }
+
+/// Origin of a bridged collateral
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Provenance {
+    pub bridge: Bridge,
+    /// Chain the token is native to
+    pub origin_chain: String,
+    /// Denom or contract address of the token on its origin chain
+    pub origin_asset: String,
+}
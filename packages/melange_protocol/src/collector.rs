@@ -0,0 +1,210 @@
+use cosmwasm_std::{attr, Attribute, Decimal, StdError, StdResult, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::asset::Asset;
+use crate::common::{PageRequest, PageResponse};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub owner: String,
+    pub mint_contract: String,
+    pub base_denom: String,
+    pub distribution_splits: Vec<DistributionSplit>,
+    /// MEL token protocol fees may be paid in
+    pub mel_token: Option<String>,
+    pub mel_fee_policy: MelFeePolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Update config; only owner (governance) is allowed to execute it
+    UpdateConfig {
+        owner: Option<String>,
+        mint_contract: Option<String>,
+        base_denom: Option<String>,
+        distribution_splits: Option<Vec<DistributionSplit>>,
+        mel_token: Option<String>,
+        mel_fee_policy: Option<MelFeePolicy>,
+    },
+    /// Distribute the collected base_denom balance across the configured splits
+    Distribute {},
+    /// Record the referrer share `fee` of a `protocol_fee` sent by the mint contract. A referrer
+    /// registered as a partner earns its rebate on the whole `protocol_fee` instead of the
+    /// share; only the mint contract is allowed to execute it
+    AccrueReferralFee {
+        referrer: String,
+        fee: Asset,
+        protocol_fee: Asset,
+    },
+    /// Send all accrued referral fees to the sender
+    ClaimReferralFees {},
+    /// Burn or distribute the collected MEL balance according to the MEL fee policy
+    ProcessMelFees {},
+    // partner rebates are booked by the collector contract, which is not part of this tree;
+    // the mint contract only reports the referred fees through AccrueReferralFee
+    /// Register a partner earning `rebate_bps` basis points of the protocol fees of the
+    /// positions it referred in place of the referral share, or unregister it when
+    /// `rebate_bps` is not set; rebates accrued so far stay claimable.
+    /// Only owner is allowed to execute it
+    UpdatePartner {
+        partner: String,
+        rebate_bps: Option<u16>,
+    },
+    /// Send all accrued rebates to the sender
+    ClaimRebate {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    ReferralFees {
+        referrer: String,
+    },
+    PartnerRebates {
+        partner: String,
+    },
+    /// Registered partners and their rebate rates
+    Partners {
+        page: Option<PageRequest>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub mint_contract: String,
+    pub base_denom: String,
+    pub distribution_splits: Vec<DistributionSplit>,
+    pub mel_token: Option<String>,
+    pub mel_fee_policy: MelFeePolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralFeesResponse {
+    pub referrer: String,
+    /// accrued and not yet claimed fees, one entry per fee asset
+    pub fees: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnerRebatesResponse {
+    pub partner: String,
+    /// none once the partner is unregistered
+    pub rebate_bps: Option<u16>,
+    /// accrued and not yet claimed rebates, one entry per fee asset
+    pub rebates: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnerResponse {
+    pub partner: String,
+    pub rebate_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnersResponse {
+    pub partners: Vec<PartnerResponse>,
+    pub page: PageResponse,
+}
+
+/// What happens to protocol fees paid in MEL
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MelFeePolicy {
+    /// Burn the MEL, reducing its supply
+    Burn,
+    /// Send the MEL through the distribution splits like base_denom fees
+    Distribute,
+}
+
+/// One leg of a fee distribution, e.g. `gov_stakers` receiving 60%
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DistributionSplit {
+    pub name: String,
+    pub recipient: String,
+    pub ratio: Decimal,
+}
+
+// splits must be non-empty, uniquely named and sum up to exactly one
+pub fn assert_distribution_splits(splits: &[DistributionSplit]) -> StdResult<()> {
+    if splits.is_empty() {
+        return Err(StdError::generic_err(
+            "At least one distribution split is required",
+        ));
+    }
+
+    let mut total = Decimal::zero();
+    for (i, split) in splits.iter().enumerate() {
+        if split.ratio.is_zero() {
+            return Err(StdError::generic_err(
+                "Distribution split ratio must be positive",
+            ));
+        }
+        if splits[..i].iter().any(|s| s.name == split.name) {
+            return Err(StdError::generic_err("Duplicate distribution split name"));
+        }
+        total += split.ratio;
+    }
+
+    if total != Decimal::one() {
+        return Err(StdError::generic_err(
+            "Distribution split ratios must sum to 1",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Splits `amount` by the configured ratios, the last leg receives the rounding remainder
+/// so the whole amount is always distributed
+pub fn compute_distribution(amount: Uint128, splits: &[DistributionSplit]) -> Vec<Uint128> {
+    let mut remaining = amount;
+    splits
+        .iter()
+        .enumerate()
+        .map(|(i, split)| {
+            let leg_amount = if i + 1 == splits.len() {
+                remaining
+            } else {
+                amount * split.ratio
+            };
+            remaining = remaining.saturating_sub(leg_amount);
+            leg_amount
+        })
+        .collect()
+}
+
+/// Attributes recording a single distribution leg, keyed by the split name
+pub fn distribution_leg_attributes(
+    split: &DistributionSplit,
+    amount: Uint128,
+    denom: &str,
+) -> Vec<Attribute> {
+    vec![
+        attr(format!("{}_recipient", split.name), split.recipient.clone()),
+        attr(format!("{}_amount", split.name), amount.to_string() + denom),
+    ]
+}
+
+/// basis points of a whole protocol fee
+pub const MAX_REBATE_BPS: u16 = 10_000;
+
+// a rebate is a share of the protocol fee, so at most all of it
+pub fn assert_rebate_bps(rebate_bps: u16) -> StdResult<()> {
+    if rebate_bps > MAX_REBATE_BPS {
+        return Err(StdError::generic_err(format!(
+            "rebate_bps must be at most {}",
+            MAX_REBATE_BPS
+        )));
+    }
+
+    Ok(())
+}
+
+/// Rebate of a partner on a protocol fee, rounded down
+pub fn compute_rebate(fee_amount: Uint128, rebate_bps: u16) -> Uint128 {
+    fee_amount.multiply_ratio(rebate_bps, MAX_REBATE_BPS)
+}
@@ -1,7 +1,8 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-use cosmwasm_std::Order;
+use cosmwasm_std::{Order, StdError, StdResult};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -30,6 +31,63 @@ impl From<OrderBy> for Order {
     }
 }
 
+/// Page of a list query. `cursor` is the `next_cursor` returned with the previous page,
+/// `order` defaults to the natural order of the endpoint
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PageRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub order: Option<OrderBy>,
+}
+
+impl PageRequest {
+    /// Decodes the cursor into the key type of the endpoint
+    pub fn parse_cursor<T: FromStr>(&self) -> StdResult<Option<T>> {
+        self.cursor
+            .as_deref()
+            .map(|cursor| {
+                cursor
+                    .parse()
+                    .map_err(|_| StdError::generic_err(format!("Invalid page cursor: {}", cursor)))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PageResponse {
+    /// cursor of the following page, none once the list is exhausted
+    pub next_cursor: Option<String>,
+    /// the read stopped at its iteration cap before filling the page,
+    /// `next_cursor` continues right after the last entry read
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl PageResponse {
+    /// Page holding `len` entries out of a requested `limit`, `last` being the key of the
+    /// last one; a short page means there is nothing left to return
+    pub fn new<T: ToString>(last: Option<T>, len: usize, limit: usize) -> Self {
+        PageResponse {
+            next_cursor: if len < limit {
+                None
+            } else {
+                last.map(|key| key.to_string())
+            },
+            truncated: false,
+        }
+    }
+
+    /// Page of a read cut short by its iteration cap, `last` being the key of the last entry
+    /// read; the page may hold fewer entries than requested and still have a following one
+    pub fn truncated<T: ToString>(last: Option<T>) -> Self {
+        PageResponse {
+            next_cursor: last.map(|key| key.to_string()),
+            truncated: true,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Network {
@@ -0,0 +1,30 @@
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::asset::{Asset, AssetInfo};
+
+/// Swap interface of the dex router the mint contract trades through
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Swap the attached native `offer_asset` for `ask_asset_info`,
+    /// failing if less than `minimum_receive` would be returned
+    Swap {
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    /// Swap the sent cw20 tokens for `ask_asset_info`
+    Swap {
+        ask_asset_info: AssetInfo,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+    },
+}
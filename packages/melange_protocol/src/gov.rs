@@ -0,0 +1,56 @@
+//! Vote delegation messages of the governance contract. The governance contract is not
+//! part of this tree, these only pin down the interface it is expected to expose
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{PageRequest, PageResponse};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Delegate the sender's voting power to another address; replaces any existing delegation.
+    /// Delegated power is counted for the delegate at each poll's snapshot
+    DelegateVotingPower { delegate: String },
+    /// Revoke the sender's delegation; can be executed at any time
+    RevokeDelegation {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Delegation {
+        delegator: String,
+    },
+    Delegators {
+        delegate: String,
+        page: Option<PageRequest>,
+    },
+    /// Voting power of the address, at the snapshot of the poll if given
+    VotingPower {
+        address: String,
+        poll_id: Option<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationResponse {
+    pub delegator: String,
+    pub delegate: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegatorsResponse {
+    pub delegate: String,
+    pub delegators: Vec<String>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPowerResponse {
+    pub address: String,
+    /// staked balance of the address itself, zero while it is delegated away
+    pub own_power: Uint128,
+    /// sum of the staked balances delegated to the address
+    pub delegated_power: Uint128,
+}
@@ -0,0 +1,25 @@
+use cosmwasm_std::{Binary, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Version of the transfer channels between the mint contract and the voucher contracts
+/// minting the synthetics on remote chains
+pub const IBC_APP_VERSION: &str = "melange-transfer-1";
+
+/// Packet moving a synthetic between chains, in both directions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferPacket {
+    /// cw20 address of the asset on the home chain
+    pub asset_token: String,
+    pub amount: Uint128,
+    pub sender: String,
+    pub receiver: String,
+}
+
+/// Acknowledgement of a transfer packet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferAck {
+    Result(Binary),
+    Error(String),
+}
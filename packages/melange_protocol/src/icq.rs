@@ -0,0 +1,23 @@
+use cosmwasm_std::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Query interface of the interchain query relayer contract. The relayer registers the
+/// interchain queries against the remote chain, verifies the proofs submitted with their
+/// results and decodes them into prices, so consumers only ever read verified values
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// Latest verified price of the registered query
+    Price { query_id: u64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub query_id: u64,
+    pub rate: Decimal,
+    /// Remote chain height the price was proven at
+    pub remote_height: u64,
+    /// Block time the result was submitted at on this chain
+    pub last_updated: u64,
+}
@@ -0,0 +1,53 @@
+//! Interface of the insurance fund contract, funded by a share of the protocol fees and
+//! drawn on to cover bad debt. The contract itself is not part of this tree yet, only the
+//! messages it is expected to accept are defined here
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub owner: String,
+    pub mint_contract: String,
+    pub base_denom: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Update config; only owner (governance) is allowed to execute it
+    UpdateConfig {
+        owner: Option<String>,
+        mint_contract: Option<String>,
+        base_denom: Option<String>,
+    },
+    /// Pay out base_denom to cover bad debt recorded by the mint contract.
+    /// Allowed for the owner, or for anyone while the mint contract is in recovery mode.
+    /// The corresponding bad debt entry is burned on the mint contract
+    CoverBadDebt {
+        asset_token: String,
+        amount: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    State {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub mint_contract: String,
+    pub base_denom: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    /// base_denom currently held by the fund
+    pub balance: Uint128,
+    /// cumulative base_denom paid out to cover bad debt
+    pub total_covered: Uint128,
+}
@@ -2,3 +2,15 @@ pub mod mint;
 pub mod collateral_oracle;
 pub mod asset;
 pub mod common;
+pub mod collector;
+pub mod insurance_fund;
+pub mod buyback;
+pub mod savings;
+pub mod staking;
+pub mod gov;
+pub mod attributes;
+pub mod position_nft;
+pub mod dex;
+pub mod ibc;
+pub mod icq;
+pub mod oracle;
@@ -1,10 +1,10 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Attribute, Binary, Coin, Decimal, Event, StdError, StdResult, SubMsg, Uint128};
 use cw20::Cw20ReceiveMsg;
 
-use crate::common::OrderBy;
+use crate::common::{PageRequest, PageResponse};
 use crate::asset::{Asset, AssetInfo};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -12,12 +12,35 @@ pub struct InstantiateMsg {
     pub owner: String,
     pub staking: String,
     pub base_denom: String,
+    /// Further base denoms positions can be quoted and fees charged in, priced against
+    /// `base_denom` by the collateral oracle; requires `dex_contract`, protocol fees
+    /// charged in them are sold for `base_denom` before reaching the collector
+    pub quote_denoms: Option<Vec<String>>,
     pub token_code_id: u64,
     pub protocol_fee_rate: Decimal,
+    pub referral_fee_rate: Decimal,
+    pub closed_position_retention: u64,
+    /// Defaults to `QueryLimits::default()` when not given
+    pub query_limits: Option<QueryLimits>,
+    /// Companion cw721 contract; when set, every opened position is minted as an NFT
+    pub position_nft: Option<String>,
+    /// Flash mints are disabled while no fee rate is set
+    pub flash_mint_fee_rate: Option<Decimal>,
+    /// dex router used by the leverage helpers
+    pub dex_contract: Option<String>,
+    /// Governance token burns can pay their protocol fee in, when set
+    pub mel_token: Option<String>,
+    /// Discount on the protocol fee when it is paid in MEL, below 1
+    pub mel_fee_discount: Decimal,
+    /// Can freeze and unfreeze positions alongside the owner
+    pub guardian: Option<String>,
+    /// Scheduler allowed to run `ExecuteMsg::HealthCheck`
+    pub cron: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
 
@@ -32,11 +55,62 @@ pub enum ExecuteMsg {
         token_code_id: Option<u64>,
         protocol_fee_rate: Option<Decimal>,
         staking: Option<String>,
+        referral_fee_rate: Option<Decimal>,
+        closed_position_retention: Option<u64>,
+        query_limits: Option<QueryLimits>,
+        /// Positions opened from now on are minted on this contract, positions minted
+        /// before stay on the contract they were minted on
+        position_nft: Option<String>,
+        /// Stops tokenizing new positions, tokenized positions stay on their contract
+        remove_position_nft: Option<bool>,
+        /// Sets the flash mint fee rate, enabling flash mints
+        flash_mint_fee_rate: Option<Decimal>,
+        /// Disables flash mints until a fee rate is set again
+        disable_flash_mint: Option<bool>,
+        dex_contract: Option<String>,
+        /// Unsets the dex contract, turning leverage, deleverage and close with swap off;
+        /// refused while quote denoms are configured
+        remove_dex_contract: Option<bool>,
+        mel_token: Option<String>,
+        /// Stops accepting protocol fees paid in MEL
+        remove_mel_token: Option<bool>,
+        mel_fee_discount: Option<Decimal>,
+        guardian: Option<String>,
+        /// Unsets the guardian, leaving freezes and mint pauses to the owner
+        remove_guardian: Option<bool>,
+        cron: Option<String>,
+        /// Unsets the cron account, leaving the health check without a caller
+        remove_cron: Option<bool>,
+        quote_denoms: Option<Vec<String>>,
     },
     /// Update asset related parameters
     UpdateAsset {
         asset_token: String,
         min_collateral_ratio: Option<Decimal>,
+        /// Share of every mint sent to the collector instead of the minter
+        mint_fee_rate: Option<Decimal>,
+        /// Protocol fee rate charged on burns of the asset, replacing the config
+        /// `protocol_fee_rate`
+        burn_fee_rate: Option<Decimal>,
+    },
+    /// Price the asset as a weighted basket of other registered assets instead of by its own
+    /// oracle feed, or back by its feed when `basket` is not set; only owner is allowed
+    /// to execute it
+    UpdateAssetBasket {
+        asset_token: String,
+        basket: Option<Vec<BasketComponent>>,
+    },
+    /// Price the asset from separate oracle feeds when minting and when liquidating, or from
+    /// its own feed for both when `price_feeds` is not set; only owner is allowed to execute it
+    UpdateAssetPriceFeeds {
+        asset_token: String,
+        price_feeds: Option<DualPriceFeeds>,
+    },
+    /// Bound the total amount of a collateral locked across all positions, or lift the bound
+    /// when `cap` is not set; only owner is allowed to execute it
+    UpdateCollateralCap {
+        collateral: AssetInfo,
+        cap: Option<Uint128>,
     },
     /// Generate asset token initialize msg and register required infos except token address
     RegisterAsset {
@@ -47,6 +121,10 @@ pub enum ExecuteMsg {
         asset_token: String,
         end_price: Decimal,
     },
+    /// Replace the protocol fee discount tiers for MEL stakers; only owner is allowed to execute it
+    UpdateFeeDiscountTiers {
+        tiers: Vec<FeeDiscountTier>,
+    },
     //////////////////////
     /// User Operations
     //////////////////////
@@ -55,6 +133,14 @@ pub enum ExecuteMsg {
         collateral: Asset,
         asset_info: AssetInfo,
         collateral_ratio: Decimal,
+        referrer: Option<String>,
+        /// Block time (seconds) after which the message is rejected
+        deadline: Option<u64>,
+        /// Reject the message if the oracle prices at execution would mint less to the sender,
+        /// after the asset mint fee
+        min_mint_amount: Option<Uint128>,
+        /// Short name the owner gives the position, e.g. "hedge"
+        label: Option<String>,
     },
     /// Deposit more collateral
     Deposit {
@@ -65,11 +151,134 @@ pub enum ExecuteMsg {
     Withdraw {
         position_idx: Uint128,
         collateral: Option<Asset>,
+        deadline: Option<u64>,
     },
     /// Convert all deposit collateral to asset
     Mint {
         position_idx: Uint128,
         asset: Asset,
+        deadline: Option<u64>,
+    },
+    /// Archive and remove positions closed for longer than the retention period; anyone can execute it
+    Prune {
+        limit: Option<u32>,
+    },
+    /// Rewrite the next batch of an unfinished storage migration; anyone can execute it
+    ContinueMigration {},
+    /// Run the `SudoMsg::HealthCheck` housekeeping; only the cron is allowed to execute it
+    HealthCheck {},
+    /// Hand a tokenized position over to the new NFT owner;
+    /// only the position NFT contract is allowed to execute it
+    TransferPosition {
+        position_idx: Uint128,
+        recipient: String,
+    },
+    /// Register the collateral ratio below which the position is listed by
+    /// `QueryMsg::TriggeredAlerts`, or remove the alert when `ratio` is not given;
+    /// only the position owner is allowed to execute it
+    SetAlert {
+        position_idx: Uint128,
+        ratio: Option<Decimal>,
+    },
+    /// Block withdrawing, minting against and transferring a position while an incident
+    /// is handled, burn and deposit remain allowed; only owner or guardian is allowed
+    /// to execute it
+    FreezePosition {
+        position_idx: Uint128,
+        reason: String,
+    },
+    /// Lift the freeze of a position; only owner or guardian is allowed to execute it
+    UnfreezePosition {
+        position_idx: Uint128,
+    },
+    /// Pause minting the asset when its supply exceeds the debt backing it, see
+    /// `QueryMsg::SupplyCheck`; only owner or guardian is allowed to execute it
+    CheckSupply {
+        asset_token: String,
+    },
+    /// Resume minting an asset paused by `CheckSupply`; only owner is allowed to execute it
+    UnpauseMint {
+        asset_token: String,
+    },
+    /// Switch the integrator allow-list on or off and add or remove integrators; while it is
+    /// on only allow-listed contracts can open positions, accounts are unaffected;
+    /// only owner is allowed to execute it
+    UpdateIntegrators {
+        enabled: Option<bool>,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+    },
+    /// Set or, when `label` is not given, clear the label of a position;
+    /// only the position owner is allowed to execute it
+    SetPositionLabel {
+        position_idx: Uint128,
+        label: Option<String>,
+    },
+    /// Mint `amount` of the asset to the sender contract and execute `callback_msg` on it;
+    /// the callback has to cw20 transfer `amount` plus the flash mint fee back to this contract
+    FlashMint {
+        asset: String,
+        amount: Uint128,
+        callback_msg: Binary,
+    },
+    /// Open a position with the attached native collateral and lever it up to
+    /// `target_leverage` times the collateral by minting, selling the asset on the dex
+    /// and depositing the proceeds in a bounded number of rounds.
+    /// `max_spread` bounds each sale against the oracle price
+    Leverage {
+        collateral: Asset,
+        asset_info: AssetInfo,
+        target_leverage: Decimal,
+        max_spread: Decimal,
+    },
+    /// Unwind the position towards `target_ratio` by withdrawing spare collateral,
+    /// buying the asset on the dex and burning it in a bounded number of rounds.
+    /// `max_spread` bounds each purchase, and the sale of a quote denom protocol fee,
+    /// against the oracle price
+    Deleverage {
+        position_idx: Uint128,
+        target_ratio: Decimal,
+        max_spread: Decimal,
+    },
+    /// Repay the whole debt by selling just enough collateral on the dex to buy it back,
+    /// and return the remaining collateral. `max_spread` bounds the sale, and the sale of a
+    /// quote denom protocol fee, against the oracle price
+    CloseWithSwap {
+        position_idx: Uint128,
+        max_spread: Decimal,
+        /// Reject the message if more collateral than this would be sold to repay the debt
+        max_collateral_sold: Option<Uint128>,
+    },
+    /// Let `key` deposit to and burn against the position until `expires_at` (seconds), at most
+    /// `daily_deposit_cap` of collateral and `daily_burn_cap` of asset a day. Session keys
+    /// can never withdraw, mint or transfer, and lapse when the position changes hands.
+    /// A position can have at most 10 session keys
+    AuthorizeSessionKey {
+        position_idx: Uint128,
+        key: String,
+        expires_at: u64,
+        daily_deposit_cap: Uint128,
+        daily_burn_cap: Uint128,
+    },
+    RevokeSessionKey {
+        position_idx: Uint128,
+        key: String,
+    },
+    /// Sends the asset to `receiver` on the chain at the other end of `channel`. The asset
+    /// is burned through `BurnFrom`, so the sender must grant this contract an allowance
+    IbcTransfer {
+        asset_token: String,
+        amount: Uint128,
+        channel: String,
+        receiver: String,
+        /// Seconds until the transfer times out and is refunded, 600 by default
+        timeout: Option<u64>,
+    },
+    /// Add or remove counterparty ports transfer channels can be opened with;
+    /// only owner is allowed to execute it
+    UpdateIbcCounterpartyPorts {
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
     },
 }
 
@@ -80,13 +289,32 @@ pub enum Cw20HookMsg {
     OpenPosition {
         asset_info: AssetInfo,
         collateral_ratio: Decimal,
+        referrer: Option<String>,
+        /// Block time (seconds) after which the message is rejected
+        deadline: Option<u64>,
+        /// Reject the message if the oracle prices at execution would mint less to the sender,
+        /// after the asset mint fee
+        min_mint_amount: Option<Uint128>,
+        /// Short name the owner gives the position, e.g. "hedge"
+        label: Option<String>,
     },
     /// Deposit more collateral
     Deposit { position_idx: Uint128 },
-    /// Convert specified asset amount and send back to user
-    Burn { position_idx: Uint128 },
+    /// Convert specified asset amount and send back to user. With `pay_fee_in_mel` the
+    /// discounted protocol fee is pulled in MEL from the sender's allowance to this contract
+    /// instead of out of the position's collateral. A protocol fee charged in a quote denom
+    /// is sold for base_denom, for at least its oracle value less `max_spread`, which is
+    /// required for such positions
+    Burn {
+        position_idx: Uint128,
+        pay_fee_in_mel: Option<bool>,
+        max_spread: Option<Decimal>,
+    },
     /// Buy discounted collateral from the contract with their asset tokens
-    Auction { position_idx: Uint128 },
+    Auction {
+        position_idx: Uint128,
+        deadline: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -96,17 +324,105 @@ pub enum QueryMsg {
     AssetConfig {
         asset_token: String,
     },
+    /// Debt and collateral of all the positions of an asset, maintained as positions change
+    AssetTotals {
+        asset_token: String,
+    },
     Position {
         position_idx: Uint128,
     },
     Positions {
         owner_addr: Option<String>,
         asset_token: Option<String>,
-        start_after: Option<Uint128>,
-        limit: Option<u32>,
-        order_by: Option<OrderBy>,
+        page: Option<PageRequest>,
+    },
+    /// Same filters as `Positions`, but only the idx, asset token and amounts of each entry
+    PositionSummaries {
+        owner_addr: Option<String>,
+        asset_token: Option<String>,
+        page: Option<PageRequest>,
     },
     NextPositionIdx {},
+    /// cw721 metadata of a tokenized position, `token_id` being the decimal position idx;
+    /// returns `position_nft::NftInfoResponse`
+    PositionNftInfo {
+        token_id: String,
+    },
+    /// Positions below the minimum collateral ratio, lowest collateral ratio first unless the
+    /// page order is descending. A page visits a bounded number of positions, so it may be
+    /// short or empty and flagged truncated while more follow
+    UnderwaterPositions {
+        asset_token: String,
+        collateral: AssetInfo,
+        page: Option<PageRequest>,
+    },
+    ArchivedPosition {
+        position_idx: Uint128,
+    },
+    /// Protocol fee rate, pending and historically paid protocol fees of an open position
+    PositionFees {
+        position_idx: Uint128,
+    },
+    /// Every state-changing action on a position since its journal started, oldest first
+    /// unless the page asks otherwise; kept once the position is closed or pruned
+    PositionJournal {
+        position_idx: Uint128,
+        page: Option<PageRequest>,
+    },
+    /// Collateral and debt of a position valued in one of the base denoms,
+    /// `base_denom` when not given
+    PositionValue {
+        position_idx: Uint128,
+        quote_denom: Option<String>,
+    },
+    /// Cap of a collateral and the amount of it locked across all positions
+    CollateralCap {
+        collateral: AssetInfo,
+    },
+    /// Reconcile the token supply of the asset with the debt of the positions minting it
+    SupplyCheck {
+        asset_token: String,
+    },
+    /// Run an execute message as `sender` without persisting anything, returning the
+    /// messages, attributes and events it would produce or the error it would fail with.
+    /// Replies to the produced submessages are not simulated
+    SimulateExecute {
+        sender: String,
+        funds: Vec<Coin>,
+        msg: Box<ExecuteMsg>,
+    },
+    /// Successful owner, guardian and governance actions in the order they happened,
+    /// unless the page asks otherwise
+    AdminActions {
+        page: Option<PageRequest>,
+    },
+    /// Allow-listed integrator contracts, ordered by canonical address
+    Integrators {
+        page: Option<PageRequest>,
+    },
+    /// Ascending position order unless the page asks otherwise
+    FrozenPositions {
+        page: Option<PageRequest>,
+    },
+    /// Positions with an alert whose collateral ratio is currently below the alert ratio,
+    /// in ascending position order unless the page asks otherwise. A page visits a bounded
+    /// number of alerts, so it may be short or empty and flagged truncated while more follow
+    TriggeredAlerts {
+        page: Option<PageRequest>,
+    },
+    ReferredPositions {
+        referrer: String,
+        page: Option<PageRequest>,
+    },
+    FeeDiscountTiers {},
+    SessionKeys {
+        position_idx: Uint128,
+    },
+    /// Amount of the asset sent over the channel and not yet returned
+    IbcOutstanding {
+        channel: String,
+        asset_token: String,
+    },
 }
 
 // We define a custom struct for each query response
@@ -120,8 +436,19 @@ pub struct ConfigResponse {
     pub melange_factory: String,
     pub lock: String,
     pub base_denom: String,
+    pub quote_denoms: Vec<String>,
     pub token_code_id: u64,
     pub protocol_fee_rate: Decimal,
+    pub referral_fee_rate: Decimal,
+    pub closed_position_retention: u64,
+    pub query_limits: QueryLimits,
+    pub position_nft: Option<String>,
+    pub flash_mint_fee_rate: Option<Decimal>,
+    pub dex_contract: Option<String>,
+    pub mel_token: Option<String>,
+    pub mel_fee_discount: Decimal,
+    pub guardian: Option<String>,
+    pub cron: Option<String>,
 }
 
 // We define a custom struct for each query response
@@ -130,6 +457,30 @@ pub struct AssetConfigResponse {
     pub token: String,
     pub min_collateral_ratio: Decimal,
     pub end_price: Option<Decimal>,
+    pub mint_fee_rate: Decimal,
+    /// the config `protocol_fee_rate` applies when not set
+    pub burn_fee_rate: Option<Decimal>,
+    pub mint_paused: bool,
+    pub basket: Option<Vec<BasketComponent>>,
+    pub price_feeds: Option<DualPriceFeeds>,
+}
+
+/// Oracle feeds pricing an asset instead of its own feed. `mint_feed` prices opening,
+/// minting, withdrawing and leveraging, so a conservative feed such as a TWAP high limits
+/// over-minting; `liquidation_feed` prices underwater checks, so a protective feed such as
+/// a TWAP low avoids liquidating on a spike. Every other action keeps the asset feed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DualPriceFeeds {
+    pub mint_feed: String,
+    pub liquidation_feed: String,
+}
+
+/// `weight` units of `asset_token` per unit of the basket asset; the basket price is the sum
+/// of the weighted component prices, recomputed whenever it is read
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BasketComponent {
+    pub asset_token: String,
+    pub weight: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -138,4 +489,318 @@ pub struct PositionResponse {
     pub owner: String,
     pub collateral: Asset,
     pub asset: Asset,
+    pub referrer: Option<String>,
+    /// whether the position is represented by an NFT of the position NFT contract
+    pub tokenized: bool,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SupplyCheckResponse {
+    pub asset_token: String,
+    /// supply of the token on this chain
+    pub total_supply: Uint128,
+    /// burned here to be sent over IBC and not yet returned, still backed by debt
+    pub ibc_outstanding: Uint128,
+    /// asset owed by all positions
+    pub total_debt: Uint128,
+    /// supply not backed by debt; the invariant holds while it is zero
+    pub surplus: Uint128,
+    /// debt without matching supply, e.g. tokens burned outside of the protocol
+    pub deficit: Uint128,
+    /// false while the asset totals are backfilled, the check is not meaningful until then
+    pub totals_complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IntegratorsResponse {
+    /// whether contracts need to be allow-listed to open positions
+    pub enabled: bool,
+    pub integrators: Vec<String>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateExecuteResponse {
+    pub messages: Vec<SubMsg>,
+    pub attributes: Vec<Attribute>,
+    pub events: Vec<Event>,
+    pub data: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminActionResponse {
+    pub id: Uint128,
+    pub height: u64,
+    pub time: u64,
+    pub actor: String,
+    /// snake case name of the execute or migrate message
+    pub action: String,
+    /// hex encoded sha256 of the JSON message, to match against the transaction
+    pub payload_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminActionsResponse {
+    pub actions: Vec<AdminActionResponse>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralCapResponse {
+    pub collateral: AssetInfo,
+    pub cap: Option<Uint128>,
+    pub total_collateral: Uint128,
+    /// false while the totals are being backfilled and undercount
+    pub totals_complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionFeesResponse {
+    pub position_idx: Uint128,
+    /// protocol fee rate applying to the owner, after the MEL staker discount
+    pub protocol_fee_rate: Decimal,
+    /// protocol fee, in collateral, burning the whole debt would be charged at current prices
+    pub pending_fee: Asset,
+    /// protocol and mint fees charged so far, one entry per fee asset
+    pub paid_fees: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JournalEntryResponse {
+    pub seq: Uint128,
+    pub height: u64,
+    pub time: u64,
+    /// snake case name of the action, as in the `action` attribute it emitted
+    pub action: String,
+    /// owner once the action executed
+    pub owner: String,
+    /// collateral the action added to or took out of the position
+    pub collateral_amount: Uint128,
+    /// asset the action minted or burned against the position
+    pub asset_amount: Uint128,
+    /// oracle prices in base_denom the action used, if any
+    pub asset_price: Option<Decimal>,
+    pub collateral_price: Option<Decimal>,
+    /// protocol and mint fees the action charged
+    pub fees: Vec<Asset>,
+    /// collateral and debt of the position once the action executed
+    pub collateral_after: Uint128,
+    pub asset_after: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionJournalResponse {
+    pub position_idx: Uint128,
+    pub collateral: AssetInfo,
+    pub asset: AssetInfo,
+    pub entries: Vec<JournalEntryResponse>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionValueResponse {
+    pub position_idx: Uint128,
+    pub quote_denom: String,
+    /// price of one unit of `quote_denom` in `base_denom`
+    pub quote_rate: Decimal,
+    pub collateral_value: Uint128,
+    pub debt_value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TriggeredAlert {
+    pub position_idx: Uint128,
+    pub owner: String,
+    pub alert_ratio: Decimal,
+    pub collateral_ratio: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TriggeredAlertsResponse {
+    pub alerts: Vec<TriggeredAlert>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenPositionResponse {
+    pub position_idx: Uint128,
+    pub reason: String,
+    pub frozen_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FrozenPositionsResponse {
+    pub positions: Vec<FrozenPositionResponse>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionSummary {
+    pub idx: Uint128,
+    pub asset_token: String,
+    pub asset_amount: Uint128,
+    pub collateral_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionSummariesResponse {
+    pub positions: Vec<PositionSummary>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SessionKeyResponse {
+    pub key: String,
+    pub expires_at: u64,
+    pub daily_deposit_cap: Uint128,
+    pub daily_burn_cap: Uint128,
+    /// Day (block time / 86400) the spent amounts below belong to
+    pub day: u64,
+    pub deposited: Uint128,
+    pub burned: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SessionKeysResponse {
+    pub position_idx: Uint128,
+    pub keys: Vec<SessionKeyResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcOutstandingResponse {
+    pub channel: String,
+    pub asset_token: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArchivedPositionResponse {
+    pub idx: Uint128,
+    pub owner: String,
+    pub collateral: AssetInfo,
+    pub asset: AssetInfo,
+    pub closed_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    /// Housekeeping run by the chain's scheduler each interval: prunes a page of positions
+    /// closed past the retention period and emits a `heartbeat` event with protocol stats
+    HealthCheck {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrateMsg {
+    /// Rewrite the legacy storage layout `batch_size` positions at a time; the first batch
+    /// runs within the migration, the rest through `ExecuteMsg::ContinueMigration`
+    RewriteStorage { batch_size: u32 },
+    /// Recount the asset totals from the stored positions `batch_size` positions at a time,
+    /// needed once when upgrading from a version without them; continues the same way
+    BackfillTotals { batch_size: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AssetTotalsResponse {
+    pub asset_token: String,
+    pub total_minted: Uint128,
+    /// one entry per collateral type
+    pub total_collateral: Vec<Asset>,
+    /// false while a backfill is still counting existing positions
+    pub complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionsResponse {
+    pub positions: Vec<PositionResponse>,
+    pub page: PageResponse,
+}
+
+/// Protocol fee multiplier applied to payers with at least `min_staked` MEL staked
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeDiscountTier {
+    pub min_staked: Uint128,
+    pub fee_multiplier: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeDiscountTiersResponse {
+    pub tiers: Vec<FeeDiscountTier>,
+}
+
+/// Page size applied to a list query when the caller gives no limit (`default`),
+/// and the hard cap applied to any limit the caller requests (`max`)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub struct QueryLimit {
+    pub default: u32,
+    pub max: u32,
+}
+
+impl QueryLimit {
+    pub const DEFAULT_LIMIT: u32 = 10;
+    pub const MAX_LIMIT: u32 = 30;
+
+    /// Resolves the requested limit into the number of entries to return
+    pub fn resolve(&self, limit: Option<u32>) -> usize {
+        limit.unwrap_or(self.default).min(self.max) as usize
+    }
+}
+
+impl Default for QueryLimit {
+    fn default() -> Self {
+        QueryLimit {
+            default: QueryLimit::DEFAULT_LIMIT,
+            max: QueryLimit::MAX_LIMIT,
+        }
+    }
+}
+
+/// Limits of each list query, `positions` also covers the owner and asset filtered variants;
+/// `prune_closed_positions` bounds the positions a single prune archives
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct QueryLimits {
+    pub positions: QueryLimit,
+    pub referred_positions: QueryLimit,
+    pub underwater_positions: QueryLimit,
+    #[serde(default)]
+    pub admin_actions: QueryLimit,
+    #[serde(default)]
+    pub position_journal: QueryLimit,
+    #[serde(default)]
+    pub integrators: QueryLimit,
+    #[serde(default)]
+    pub frozen_positions: QueryLimit,
+    #[serde(default)]
+    pub triggered_alerts: QueryLimit,
+    #[serde(default)]
+    pub prune_closed_positions: QueryLimit,
+}
+
+impl QueryLimits {
+    pub fn assert_valid(&self) -> StdResult<()> {
+        for limit in [
+            &self.positions,
+            &self.referred_positions,
+            &self.underwater_positions,
+            &self.admin_actions,
+            &self.position_journal,
+            &self.integrators,
+            &self.frozen_positions,
+            &self.triggered_alerts,
+            &self.prune_closed_positions,
+        ] {
+            if limit.max == 0 || limit.default == 0 {
+                return Err(StdError::generic_err("Query limits must be positive"));
+            }
+            if limit.default > limit.max {
+                return Err(StdError::generic_err(
+                    "Default query limit must not exceed the maximum",
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
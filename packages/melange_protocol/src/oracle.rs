@@ -0,0 +1,104 @@
+use cosmwasm_std::{Decimal, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{PageRequest, PageResponse};
+
+/// Interface of the price oracle contract the mint contract reads asset prices from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Feed the prices of several assets at once, as (asset, price) pairs; the sender has to
+    /// be the registered feeder of every asset, feeds of replaced feeders are rejected
+    FeedPrice { prices: Vec<(String, Decimal)> },
+    // the feeder registry is implemented by the oracle contract, which is not part of this
+    // tree; the mock oracle in melange_testing rejects these messages
+    /// Register or replace the feeder of an asset, dropping any pending handover;
+    /// only owner is allowed to execute it
+    RegisterFeeder { asset_token: String, feeder: String },
+    /// Start handing the asset over to `feeder`, the current feeder keeps feeding until
+    /// the handover is accepted; owner or the current feeder is allowed to execute it
+    ProposeFeeder { asset_token: String, feeder: String },
+    /// Take over feeding the asset; only the pending feeder is allowed to execute it
+    AcceptFeeder { asset_token: String },
+    // feeder incentives are paid by the oracle contract as well and are not implemented in
+    // this tree either
+    /// Configure the feeder incentives, or disable them when `incentive` is not given;
+    /// only owner is allowed to execute it
+    UpdateIncentive { incentive: Option<FeederIncentive> },
+    /// Add the attached coins of the incentive denom to the reward pool, e.g. from a
+    /// collector distribution split
+    FundIncentive {},
+    /// Send the rewards the sender earned as a feeder
+    ClaimFeederRewards {},
+}
+
+/// Reward earned by a feeder per price update. An update is rewarded when the previous
+/// feed of the asset is at most `staleness_window` seconds old, so feeders are paid for
+/// keeping prices continuously fresh, and at least `min_reward_interval` seconds passed
+/// since the last rewarded update of the asset, so spamming updates earns nothing extra.
+/// Rewards only accrue while the pool still covers them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederIncentive {
+    pub denom: String,
+    pub reward_per_update: Uint128,
+    pub min_reward_interval: u64,
+    pub staleness_window: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// Price of the asset, failing if it was last fed more than `timeframe` seconds ago
+    Price {
+        asset_token: String,
+        timeframe: Option<u64>,
+    },
+    Feeder {
+        asset_token: String,
+    },
+    /// Ordered by asset token
+    Feeders {
+        page: Option<PageRequest>,
+    },
+    Incentive {},
+    FeederRewards {
+        feeder: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub rate: Decimal,
+    pub last_updated: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederResponse {
+    pub asset_token: String,
+    pub feeder: String,
+    pub pending_feeder: Option<String>,
+    /// block time of the last feed of the asset, if any
+    pub last_updated: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeedersResponse {
+    pub feeders: Vec<FeederResponse>,
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IncentiveResponse {
+    pub incentive: Option<FeederIncentive>,
+    /// funds not yet earned by any feeder
+    pub pool: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeederRewardsResponse {
+    pub feeder: String,
+    /// earned and not yet claimed
+    pub pending_rewards: Uint128,
+    pub rewarded_updates: u64,
+}
@@ -0,0 +1,39 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Messages the mint contract sends to the companion cw721 contract representing positions.
+/// Token ids are the decimal position idx. On every transfer the companion contract is expected
+/// to call `mint::ExecuteMsg::TransferPosition`, the mint contract stays the source of truth
+/// for position ownership
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Mint the NFT of a newly opened position; only the mint contract is allowed to execute it
+    Mint { token_id: String, owner: String },
+    /// Burn the NFT of a pruned or fully closed position;
+    /// only the mint contract is allowed to execute it
+    Burn { token_id: String },
+}
+
+/// `NftInfo` response of a position, in the cw721 on-chain metadata shape so marketplaces
+/// render it without a custom integration. The companion contract answers its own `NftInfo`
+/// query with `mint::QueryMsg::PositionNftInfo`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftInfoResponse {
+    pub token_uri: Option<String>,
+    pub extension: Metadata,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub attributes: Option<Vec<Trait>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Trait {
+    pub display_type: Option<String>,
+    pub trait_type: String,
+    pub value: String,
+}
@@ -0,0 +1,76 @@
+//! Interface of the savings contract paying a rate to depositors of the base synthetic.
+//! The contract is not part of this tree yet; only its messages are defined here
+use cosmwasm_std::{Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub owner: String,
+    /// Base synthetic holders deposit; interest is paid in the same token
+    pub asset_token: String,
+    /// Annual rate, accrued per second on deposits while the reserve lasts
+    pub savings_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
+
+    /// Update config; only owner (governance) is allowed to execute it.
+    /// Interest accrued at the previous rate is settled first
+    UpdateConfig {
+        owner: Option<String>,
+        savings_rate: Option<Decimal>,
+    },
+    /// Withdraw deposited tokens along with the accrued interest;
+    /// withdraws everything when `amount` is not given
+    Withdraw {
+        amount: Option<Uint128>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    /// Lock the sent tokens to earn the savings rate
+    Deposit {},
+    /// Top up the interest reserve, e.g. by a collector distribution split routing
+    /// protocol fees; interest stops accruing while the reserve is empty
+    Fund {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    State {},
+    Deposit { address: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub asset_token: String,
+    pub savings_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    /// deposits plus accrued interest owed to depositors
+    pub total_deposits: Uint128,
+    /// funded tokens not yet accrued as interest
+    pub reserve: Uint128,
+    /// value of one deposited token since the contract was instantiated
+    pub accumulated_index: Decimal,
+    pub last_accrued: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositResponse {
+    pub address: String,
+    /// withdrawable amount, accrued interest included
+    pub balance: Uint128,
+}
@@ -0,0 +1,47 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SdkError {
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("gRPC status: {0}")]
+    Status(Box<tonic::Status>),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("protobuf decode error: {0}")]
+    Decode(#[from] cosmrs::proto::prost::DecodeError),
+
+    #[error("{0}")]
+    Cosmos(cosmrs::ErrorReport),
+
+    #[error("tendermint error: {0}")]
+    Tendermint(#[from] cosmrs::tendermint::Error),
+
+    #[error("invalid gRPC url: {0}")]
+    InvalidUrl(String),
+
+    #[error("account {0} not found")]
+    AccountNotFound(String),
+
+    #[error("transaction {txhash} failed with code {code}: {log}")]
+    TxFailed {
+        txhash: String,
+        code: u32,
+        log: String,
+    },
+}
+
+impl From<cosmrs::ErrorReport> for SdkError {
+    fn from(err: cosmrs::ErrorReport) -> Self {
+        SdkError::Cosmos(err)
+    }
+}
+
+impl From<tonic::Status> for SdkError {
+    fn from(status: tonic::Status) -> Self {
+        SdkError::Status(Box::new(status))
+    }
+}
@@ -0,0 +1,12 @@
+//! Off-chain client for melange-protocol keepers.
+//!
+//! [`query::QueryClient`] runs typed smart queries against the protocol contracts over gRPC,
+//! [`msg`] builds the `MsgExecuteContract`s for the actions bots take and
+//! [`tx::Signer`] signs and broadcasts them.
+
+pub mod error;
+pub mod msg;
+pub mod query;
+pub mod tx;
+
+pub use error::SdkError;
@@ -0,0 +1,162 @@
+//! Builders of the `MsgExecuteContract`s keepers send. cw20 amounts go through a `Send` to the
+//! mint contract carrying the matching hook message, native amounts are attached as funds.
+
+use cosmrs::{cosmwasm::MsgExecuteContract, AccountId, Coin, Denom};
+use cosmwasm_std::{Binary, Decimal, Uint128};
+use cw20::Cw20ExecuteMsg;
+use melange_protocol::{
+    asset::{Asset, AssetInfo},
+    mint::{Cw20HookMsg, ExecuteMsg as MintExecuteMsg},
+    oracle::ExecuteMsg as OracleExecuteMsg,
+};
+use serde::Serialize;
+
+use crate::error::SdkError;
+
+#[allow(clippy::too_many_arguments)]
+pub fn open_position(
+    sender: &AccountId,
+    mint: &AccountId,
+    collateral: Asset,
+    asset_info: AssetInfo,
+    collateral_ratio: Decimal,
+    referrer: Option<String>,
+    deadline: Option<u64>,
+    min_mint_amount: Option<Uint128>,
+    label: Option<String>,
+) -> Result<MsgExecuteContract, SdkError> {
+    match collateral.info.clone() {
+        AssetInfo::Token { contract_addr } => send(
+            sender,
+            &contract_addr,
+            mint,
+            collateral.amount,
+            &Cw20HookMsg::OpenPosition {
+                asset_info,
+                collateral_ratio,
+                referrer,
+                deadline,
+                min_mint_amount,
+                label,
+            },
+        ),
+        AssetInfo::NativeToken { denom } => execute(
+            sender,
+            mint,
+            &MintExecuteMsg::OpenPosition {
+                collateral: collateral.clone(),
+                asset_info,
+                collateral_ratio,
+                referrer,
+                deadline,
+                min_mint_amount,
+                label,
+            },
+            vec![coin(&denom, collateral.amount)?],
+        ),
+    }
+}
+
+pub fn deposit(
+    sender: &AccountId,
+    mint: &AccountId,
+    position_idx: Uint128,
+    collateral: Asset,
+) -> Result<MsgExecuteContract, SdkError> {
+    match collateral.info.clone() {
+        AssetInfo::Token { contract_addr } => send(
+            sender,
+            &contract_addr,
+            mint,
+            collateral.amount,
+            &Cw20HookMsg::Deposit { position_idx },
+        ),
+        AssetInfo::NativeToken { denom } => execute(
+            sender,
+            mint,
+            &MintExecuteMsg::Deposit {
+                position_idx,
+                collateral: collateral.clone(),
+            },
+            vec![coin(&denom, collateral.amount)?],
+        ),
+    }
+}
+
+/// Liquidates an underwater position by buying its discounted collateral with `amount`
+/// of the position's asset. Messages carrying a `deadline` are rejected once the block
+/// time passes it
+pub fn liquidate(
+    sender: &AccountId,
+    mint: &AccountId,
+    position_idx: Uint128,
+    asset_token: &str,
+    amount: Uint128,
+    deadline: Option<u64>,
+) -> Result<MsgExecuteContract, SdkError> {
+    send(
+        sender,
+        asset_token,
+        mint,
+        amount,
+        &Cw20HookMsg::Auction {
+            position_idx,
+            deadline,
+        },
+    )
+}
+
+pub fn feed_price(
+    sender: &AccountId,
+    oracle: &AccountId,
+    prices: Vec<(String, Decimal)>,
+) -> Result<MsgExecuteContract, SdkError> {
+    execute(
+        sender,
+        oracle,
+        &OracleExecuteMsg::FeedPrice { prices },
+        vec![],
+    )
+}
+
+/// Executes any contract message, for actions without a builder above
+pub fn execute<T: Serialize>(
+    sender: &AccountId,
+    contract: &AccountId,
+    msg: &T,
+    funds: Vec<Coin>,
+) -> Result<MsgExecuteContract, SdkError> {
+    Ok(MsgExecuteContract {
+        sender: sender.clone(),
+        contract: contract.clone(),
+        msg: serde_json::to_vec(msg)?,
+        funds,
+    })
+}
+
+// cw20 Send of `amount` to `contract` carrying the hook `msg`
+fn send<T: Serialize>(
+    sender: &AccountId,
+    token: &str,
+    contract: &AccountId,
+    amount: Uint128,
+    msg: &T,
+) -> Result<MsgExecuteContract, SdkError> {
+    execute(
+        sender,
+        &token.parse()?,
+        &Cw20ExecuteMsg::Send {
+            contract: contract.to_string(),
+            amount,
+            msg: Binary::from(serde_json::to_vec(msg)?),
+        },
+        vec![],
+    )
+}
+
+fn coin(denom: &str, amount: Uint128) -> Result<Coin, SdkError> {
+    Ok(Coin {
+        denom: denom.parse::<Denom>()?,
+        amount: amount.u128(),
+    })
+}
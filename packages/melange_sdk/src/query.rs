@@ -0,0 +1,181 @@
+use cosmrs::proto::cosmwasm::wasm::v1::{
+    query_client::QueryClient as WasmQueryClient, QuerySmartContractStateRequest,
+};
+use cosmwasm_std::Uint128;
+use melange_protocol::{
+    asset::AssetInfo,
+    collateral_oracle::{
+        CollateralAssetsResponse, CollateralFilter, CollateralPriceResponse,
+        CollateralStatusesResponse, QueryMsg as CollateralOracleQueryMsg,
+    },
+    common::PageRequest,
+    mint::{
+        AssetConfigResponse, ConfigResponse, PositionResponse, PositionsResponse,
+        QueryMsg as MintQueryMsg,
+    },
+    oracle::{PriceResponse, QueryMsg as OracleQueryMsg},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tonic::transport::Channel;
+
+use crate::error::SdkError;
+
+/// Addresses of the protocol contracts a client talks to
+#[derive(Clone, Debug)]
+pub struct Contracts {
+    pub mint: String,
+    pub oracle: String,
+    pub collateral_oracle: String,
+}
+
+/// Typed smart queries against the protocol contracts over a gRPC endpoint
+#[derive(Clone)]
+pub struct QueryClient {
+    wasm: WasmQueryClient<Channel>,
+    pub contracts: Contracts,
+}
+
+impl QueryClient {
+    pub async fn connect(grpc_url: String, contracts: Contracts) -> Result<Self, SdkError> {
+        Ok(QueryClient {
+            wasm: WasmQueryClient::connect(grpc_url).await?,
+            contracts,
+        })
+    }
+
+    /// Runs any smart query, for messages without a typed helper below
+    pub async fn smart_query<Q: Serialize, R: DeserializeOwned>(
+        &mut self,
+        contract: &str,
+        msg: &Q,
+    ) -> Result<R, SdkError> {
+        let res = self
+            .wasm
+            .smart_contract_state(QuerySmartContractStateRequest {
+                address: contract.to_string(),
+                query_data: serde_json::to_vec(msg)?,
+            })
+            .await?;
+
+        Ok(serde_json::from_slice(&res.into_inner().data)?)
+    }
+
+    pub async fn mint_config(&mut self) -> Result<ConfigResponse, SdkError> {
+        let mint = self.contracts.mint.clone();
+        self.smart_query(&mint, &MintQueryMsg::Config {}).await
+    }
+
+    pub async fn asset_config(
+        &mut self,
+        asset_token: &str,
+    ) -> Result<AssetConfigResponse, SdkError> {
+        let mint = self.contracts.mint.clone();
+        self.smart_query(
+            &mint,
+            &MintQueryMsg::AssetConfig {
+                asset_token: asset_token.to_string(),
+            },
+        )
+        .await
+    }
+
+    pub async fn position(&mut self, position_idx: Uint128) -> Result<PositionResponse, SdkError> {
+        let mint = self.contracts.mint.clone();
+        self.smart_query(&mint, &MintQueryMsg::Position { position_idx })
+            .await
+    }
+
+    pub async fn positions(
+        &mut self,
+        owner_addr: Option<String>,
+        asset_token: Option<String>,
+        page: Option<PageRequest>,
+    ) -> Result<PositionsResponse, SdkError> {
+        let mint = self.contracts.mint.clone();
+        self.smart_query(
+            &mint,
+            &MintQueryMsg::Positions {
+                owner_addr,
+                asset_token,
+                page,
+            },
+        )
+        .await
+    }
+
+    /// Liquidation candidates of an asset and collateral pair, lowest collateral ratio first
+    pub async fn underwater_positions(
+        &mut self,
+        asset_token: &str,
+        collateral: AssetInfo,
+        page: Option<PageRequest>,
+    ) -> Result<PositionsResponse, SdkError> {
+        let mint = self.contracts.mint.clone();
+        self.smart_query(
+            &mint,
+            &MintQueryMsg::UnderwaterPositions {
+                asset_token: asset_token.to_string(),
+                collateral,
+                page,
+            },
+        )
+        .await
+    }
+
+    pub async fn price(
+        &mut self,
+        asset_token: &str,
+        timeframe: Option<u64>,
+    ) -> Result<PriceResponse, SdkError> {
+        let oracle = self.contracts.oracle.clone();
+        self.smart_query(
+            &oracle,
+            &OracleQueryMsg::Price {
+                asset_token: asset_token.to_string(),
+                timeframe,
+            },
+        )
+        .await
+    }
+
+    pub async fn collateral_price(
+        &mut self,
+        asset: &str,
+        timeframe: Option<u64>,
+    ) -> Result<CollateralPriceResponse, SdkError> {
+        let collateral_oracle = self.contracts.collateral_oracle.clone();
+        self.smart_query(
+            &collateral_oracle,
+            &CollateralOracleQueryMsg::CollateralPrice {
+                asset: asset.to_string(),
+                timeframe,
+            },
+        )
+        .await
+    }
+
+    pub async fn collateral_assets(
+        &mut self,
+        filter: Option<CollateralFilter>,
+        page: Option<PageRequest>,
+    ) -> Result<CollateralAssetsResponse, SdkError> {
+        let collateral_oracle = self.contracts.collateral_oracle.clone();
+        self.smart_query(
+            &collateral_oracle,
+            &CollateralOracleQueryMsg::CollateralAssets { filter, page },
+        )
+        .await
+    }
+
+    pub async fn collateral_infos(
+        &mut self,
+        assets: Vec<AssetInfo>,
+    ) -> Result<CollateralStatusesResponse, SdkError> {
+        let collateral_oracle = self.contracts.collateral_oracle.clone();
+        self.smart_query(
+            &collateral_oracle,
+            &CollateralOracleQueryMsg::CollateralInfos { assets },
+        )
+        .await
+    }
+}
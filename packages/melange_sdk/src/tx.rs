@@ -0,0 +1,109 @@
+use cosmrs::{
+    crypto::secp256k1::SigningKey,
+    proto::{
+        cosmos::{
+            auth::v1beta1::{
+                query_client::QueryClient as AuthQueryClient, BaseAccount, QueryAccountRequest,
+            },
+            tx::v1beta1::{service_client::ServiceClient, BroadcastMode, BroadcastTxRequest},
+        },
+        prost::Message,
+    },
+    tendermint::chain,
+    tx::{self, Fee, Msg, SignDoc, SignerInfo},
+    AccountId, Coin,
+};
+use tonic::transport::Channel;
+
+use crate::error::SdkError;
+
+/// Signs transactions with a single key and broadcasts them over gRPC
+pub struct Signer {
+    key: SigningKey,
+    pub address: AccountId,
+    chain_id: chain::Id,
+    auth: AuthQueryClient<Channel>,
+    service: ServiceClient<Channel>,
+}
+
+impl Signer {
+    /// `account_prefix` is the bech32 prefix of the chain, e.g. `sei`
+    pub async fn connect(
+        grpc_url: String,
+        chain_id: &str,
+        account_prefix: &str,
+        key: SigningKey,
+    ) -> Result<Self, SdkError> {
+        let channel = Channel::from_shared(grpc_url)
+            .map_err(|err| SdkError::InvalidUrl(err.to_string()))?
+            .connect()
+            .await?;
+
+        Ok(Signer {
+            address: key.public_key().account_id(account_prefix)?,
+            key,
+            chain_id: chain_id.parse()?,
+            auth: AuthQueryClient::new(channel.clone()),
+            service: ServiceClient::new(channel),
+        })
+    }
+
+    /// Signs `msgs` into one transaction paying `fee` for `gas_limit` and waits for the
+    /// node to check it, returning the transaction hash
+    pub async fn broadcast<M: Msg>(
+        &mut self,
+        msgs: Vec<M>,
+        fee: Coin,
+        gas_limit: u64,
+        memo: &str,
+    ) -> Result<String, SdkError> {
+        let (account_number, sequence) = self.account().await?;
+
+        let msgs = msgs
+            .iter()
+            .map(|msg| msg.to_any())
+            .collect::<Result<Vec<_>, _>>()?;
+        let body = tx::Body::new(msgs, memo, 0u32);
+        let auth_info = SignerInfo::single_direct(Some(self.key.public_key()), sequence)
+            .auth_info(Fee::from_amount_and_gas(fee, gas_limit));
+        let tx_bytes = SignDoc::new(&body, &auth_info, &self.chain_id, account_number)?
+            .sign(&self.key)?
+            .to_bytes()?;
+
+        let res = self
+            .service
+            .broadcast_tx(BroadcastTxRequest {
+                tx_bytes,
+                mode: BroadcastMode::Sync as i32,
+            })
+            .await?
+            .into_inner()
+            .tx_response
+            .unwrap_or_default();
+        if res.code != 0 {
+            return Err(SdkError::TxFailed {
+                txhash: res.txhash,
+                code: res.code,
+                log: res.raw_log,
+            });
+        }
+
+        Ok(res.txhash)
+    }
+
+    // account number and next sequence of the signer
+    async fn account(&mut self) -> Result<(u64, u64), SdkError> {
+        let account = self
+            .auth
+            .account(QueryAccountRequest {
+                address: self.address.to_string(),
+            })
+            .await?
+            .into_inner()
+            .account
+            .ok_or_else(|| SdkError::AccountNotFound(self.address.to_string()))?;
+        let account = BaseAccount::decode(account.value.as_slice())?;
+
+        Ok((account.account_number, account.sequence))
+    }
+}
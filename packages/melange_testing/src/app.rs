@@ -0,0 +1,477 @@
+use anyhow::Result as AnyResult;
+use cosmwasm_std::{coins, to_binary, Addr, Coin, Decimal, Empty, Uint128};
+use cw20::{Cw20Coin, Cw20ExecuteMsg, MinterResponse};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+use melange_protocol::{
+    asset::{Asset, AssetInfo},
+    collateral_oracle::{ExecuteMsg as CollateralOracleExecuteMsg, SourceType},
+    mint::{Cw20HookMsg, ExecuteMsg as MintExecuteMsg, InstantiateMsg as MintInstantiateMsg},
+    oracle::ExecuteMsg as OracleExecuteMsg,
+};
+
+use crate::{collateral_oracle, lock, oracle, staking};
+
+/// Owner of every contract instantiated by the harness
+pub const OWNER: &str = "owner";
+const COLLECTOR: &str = "collector";
+const BASE_DENOM: &str = "usei";
+
+/// cw-multi-test `App` with the mock protocol contracts instantiated
+pub struct MelangeApp {
+    pub app: App,
+    pub owner: Addr,
+    pub collector: Addr,
+    pub oracle: Addr,
+    pub collateral_oracle: Addr,
+    pub staking: Addr,
+    pub lock: Addr,
+    pub token_code_id: u64,
+    /// Set by [`MelangeApp::instantiate_mint`]
+    pub mint: Option<Addr>,
+}
+
+impl Default for MelangeApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MelangeApp {
+    pub fn new() -> Self {
+        let mut app = App::default();
+        let owner = Addr::unchecked(OWNER);
+
+        let oracle_code_id = app.store_code(oracle::contract());
+        let collateral_oracle_code_id = app.store_code(collateral_oracle::contract());
+        let staking_code_id = app.store_code(staking::contract());
+        let lock_code_id = app.store_code(lock::contract());
+        let token_code_id = app.store_code(cw20_contract());
+
+        let oracle = app
+            .instantiate_contract(
+                oracle_code_id,
+                owner.clone(),
+                &Empty {},
+                &[],
+                "oracle",
+                None,
+            )
+            .unwrap();
+        let collateral_oracle = app
+            .instantiate_contract(
+                collateral_oracle_code_id,
+                owner.clone(),
+                &collateral_oracle::InstantiateMsg {
+                    oracle: oracle.to_string(),
+                },
+                &[],
+                "collateral oracle",
+                None,
+            )
+            .unwrap();
+        let staking = app
+            .instantiate_contract(
+                staking_code_id,
+                owner.clone(),
+                &Empty {},
+                &[],
+                "staking",
+                None,
+            )
+            .unwrap();
+        let lock = app
+            .instantiate_contract(lock_code_id, owner.clone(), &Empty {}, &[], "lock", None)
+            .unwrap();
+
+        MelangeApp {
+            app,
+            owner,
+            collector: Addr::unchecked(COLLECTOR),
+            oracle,
+            collateral_oracle,
+            staking,
+            lock,
+            token_code_id,
+            mint: None,
+        }
+    }
+
+    /// Stores and instantiates the mint contract with the default parameters below and
+    /// points it at the mock contracts
+    pub fn instantiate_mint(&mut self, contract: Box<dyn Contract<Empty>>) -> AnyResult<Addr> {
+        let code_id = self.app.store_code(contract);
+        let mint = self.app.instantiate_contract(
+            code_id,
+            self.owner.clone(),
+            &MintInstantiateMsg {
+                owner: self.owner.to_string(),
+                staking: self.staking.to_string(),
+                base_denom: BASE_DENOM.to_string(),
+                quote_denoms: None,
+                token_code_id: self.token_code_id,
+                protocol_fee_rate: Decimal::permille(15),
+                referral_fee_rate: Decimal::zero(),
+                closed_position_retention: 86400,
+                query_limits: None,
+                position_nft: None,
+                flash_mint_fee_rate: None,
+                dex_contract: None,
+                mel_token: None,
+                mel_fee_discount: Decimal::zero(),
+                guardian: None,
+                cron: None,
+            },
+            &[],
+            "mint",
+            None,
+        )?;
+
+        self.app.execute_contract(
+            self.owner.clone(),
+            mint.clone(),
+            &MintExecuteMsg::UpdateConfig {
+                owner: None,
+                oracle: Some(self.oracle.to_string()),
+                collector: Some(self.collector.to_string()),
+                collateral_oracle: Some(self.collateral_oracle.to_string()),
+                melange_factory: Some(self.owner.to_string()),
+                lock: Some(self.lock.to_string()),
+                token_code_id: None,
+                protocol_fee_rate: None,
+                staking: None,
+                referral_fee_rate: None,
+                closed_position_retention: None,
+                query_limits: None,
+                position_nft: None,
+                remove_position_nft: None,
+                flash_mint_fee_rate: None,
+                disable_flash_mint: None,
+                dex_contract: None,
+                remove_dex_contract: None,
+                mel_token: None,
+                remove_mel_token: None,
+                mel_fee_discount: None,
+                guardian: None,
+                remove_guardian: None,
+                cron: None,
+                remove_cron: None,
+                quote_denoms: None,
+            },
+            &[],
+        )?;
+
+        self.mint = Some(mint.clone());
+        Ok(mint)
+    }
+
+    /// Instantiates a cw20 token minted by the mint contract, with optional initial balances
+    pub fn create_asset_token(
+        &mut self,
+        symbol: &str,
+        initial_balances: Vec<Cw20Coin>,
+    ) -> AnyResult<Addr> {
+        let minter = self.mint()?.to_string();
+        self.app.instantiate_contract(
+            self.token_code_id,
+            self.owner.clone(),
+            &cw20_base::msg::InstantiateMsg {
+                name: format!("melange {}", symbol),
+                symbol: symbol.to_string(),
+                decimals: 6,
+                initial_balances,
+                mint: Some(MinterResponse { minter, cap: None }),
+                marketing: None,
+            },
+            &[],
+            symbol,
+            None,
+        )
+    }
+
+    /// Creates a synthetic asset token and registers it on the mint contract
+    pub fn register_asset(
+        &mut self,
+        symbol: &str,
+        min_collateral_ratio: Decimal,
+    ) -> AnyResult<Addr> {
+        let asset_token = self.create_asset_token(symbol, vec![])?;
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.mint()?,
+            &MintExecuteMsg::RegisterAsset {
+                asset_token: asset_token.to_string(),
+                min_collateral_ratio,
+            },
+            &[],
+        )?;
+
+        Ok(asset_token)
+    }
+
+    /// Registers a collateral priced by the mock oracle under `price_key`
+    pub fn register_collateral(
+        &mut self,
+        asset: AssetInfo,
+        price_key: &str,
+        multiplier: Decimal,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.collateral_oracle.clone(),
+            &CollateralOracleExecuteMsg::RegisterCollateralAsset {
+                asset,
+                price_source: SourceType::Native {
+                    native_denom: price_key.to_string(),
+                },
+                multiplier,
+            },
+            &[],
+        )
+    }
+
+    /// Feeds the price of `asset` (denom or token address) at the current block time
+    pub fn set_price(&mut self, asset: &str, price: Decimal) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.oracle.clone(),
+            &OracleExecuteMsg::FeedPrice {
+                prices: vec![(asset.to_string(), price)],
+            },
+            &[],
+        )
+    }
+
+    pub fn set_stake(&mut self, address: &Addr, balance: Uint128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.staking.clone(),
+            &staking::ExecuteMsg::SetStake {
+                address: address.to_string(),
+                balance,
+            },
+            &[],
+        )
+    }
+
+    /// Mints native coins out of thin air
+    pub fn fund(&mut self, address: &Addr, amount: u128, denom: &str) -> AnyResult<()> {
+        self.app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, address, coins(amount, denom))
+        })
+    }
+
+    /// Opens a position, sending native collateral as funds and cw20 collateral through
+    /// the `OpenPosition` hook
+    pub fn open_position(
+        &mut self,
+        sender: &Addr,
+        collateral: Asset,
+        asset_token: &Addr,
+        collateral_ratio: Decimal,
+    ) -> AnyResult<AppResponse> {
+        let mint = self.mint()?;
+        let asset_info = AssetInfo::Token {
+            contract_addr: asset_token.to_string(),
+        };
+
+        match collateral.info.clone() {
+            AssetInfo::NativeToken { denom } => self.app.execute_contract(
+                sender.clone(),
+                mint,
+                &MintExecuteMsg::OpenPosition {
+                    collateral: collateral.clone(),
+                    asset_info,
+                    collateral_ratio,
+                    referrer: None,
+                    deadline: None,
+                    min_mint_amount: None,
+                    label: None,
+                },
+                &[Coin {
+                    denom,
+                    amount: collateral.amount,
+                }],
+            ),
+            AssetInfo::Token { contract_addr } => self.app.execute_contract(
+                sender.clone(),
+                Addr::unchecked(contract_addr),
+                &Cw20ExecuteMsg::Send {
+                    contract: mint.to_string(),
+                    amount: collateral.amount,
+                    msg: to_binary(&Cw20HookMsg::OpenPosition {
+                        asset_info,
+                        collateral_ratio,
+                        referrer: None,
+                        deadline: None,
+                        min_mint_amount: None,
+                        label: None,
+                    })?,
+                },
+                &[],
+            ),
+        }
+    }
+
+    /// Moves the block time forward, along with the height at the usual 5 second block time
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.app.update_block(|block| {
+            block.time = block.time.plus_seconds(seconds);
+            block.height += seconds / 5;
+        })
+    }
+
+    fn mint(&self) -> AnyResult<Addr> {
+        self.mint
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("mint contract not instantiated"))
+    }
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use melange_protocol::{
+        collateral_oracle::{
+            CollateralPriceResponse, CollateralPricesResponse, CollateralStatusesResponse,
+            QueryMsg as CollateralOracleQueryMsg,
+        },
+        staking::{QueryMsg as StakingQueryMsg, StakerResponse},
+    };
+
+    const COLLATERAL_DENOM: &str = "uatom";
+    const ASSET_TOKEN: &str = "asset0000";
+
+    fn native(denom: &str) -> AssetInfo {
+        AssetInfo::NativeToken {
+            denom: denom.to_string(),
+        }
+    }
+
+    fn setup() -> MelangeApp {
+        let mut app = MelangeApp::new();
+        app.set_price(COLLATERAL_DENOM, Decimal::percent(1000))
+            .unwrap();
+        app.set_price(ASSET_TOKEN, Decimal::percent(250)).unwrap();
+        app.register_collateral(
+            native(COLLATERAL_DENOM),
+            COLLATERAL_DENOM,
+            Decimal::percent(80),
+        )
+        .unwrap();
+        app
+    }
+
+    #[test]
+    fn collateral_oracle_prices_registered_collateral() {
+        let app = setup();
+        let res: CollateralPriceResponse = app
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &app.collateral_oracle,
+                &CollateralOracleQueryMsg::CollateralPrice {
+                    asset: COLLATERAL_DENOM.to_string(),
+                    timeframe: Some(60),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(res.rate, Decimal::percent(1000));
+        assert_eq!(res.multiplier, Decimal::percent(80));
+        assert!(!res.is_revoked);
+    }
+
+    #[test]
+    fn collateral_oracle_prices_assets_in_one_query() {
+        let app = setup();
+        let assets = vec![
+            AssetInfo::Token {
+                contract_addr: ASSET_TOKEN.to_string(),
+            },
+            native(COLLATERAL_DENOM),
+        ];
+        let res: CollateralPricesResponse = app
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &app.collateral_oracle,
+                &CollateralOracleQueryMsg::Prices {
+                    assets,
+                    timeframe: None,
+                },
+            )
+            .unwrap();
+
+        // the unregistered asset is priced by the oracle with a multiplier of one
+        let rates: Vec<(Decimal, Decimal)> = res
+            .prices
+            .iter()
+            .map(|price| (price.rate, price.multiplier))
+            .collect();
+        assert_eq!(
+            rates,
+            vec![
+                (Decimal::percent(250), Decimal::one()),
+                (Decimal::percent(1000), Decimal::percent(80)),
+            ]
+        );
+    }
+
+    #[test]
+    fn stale_prices_fail_and_suspend() {
+        let mut app = setup();
+        app.advance_time(120);
+
+        let stale: Result<CollateralPriceResponse, _> = app.app.wrap().query_wasm_smart(
+            &app.collateral_oracle,
+            &CollateralOracleQueryMsg::CollateralPrice {
+                asset: COLLATERAL_DENOM.to_string(),
+                timeframe: Some(60),
+            },
+        );
+        assert!(stale.is_err());
+
+        let res: CollateralStatusesResponse = app
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &app.collateral_oracle,
+                &CollateralOracleQueryMsg::CollateralInfos {
+                    assets: vec![native(COLLATERAL_DENOM), native("unpriced")],
+                },
+            )
+            .unwrap();
+        assert_eq!(res.collaterals[0].age, 120);
+        assert!(!res.collaterals[0].is_suspended);
+        assert!(res.collaterals[1].is_suspended);
+    }
+
+    #[test]
+    fn staking_answers_set_stakes() {
+        let mut app = setup();
+        let staker = Addr::unchecked("staker");
+        app.set_stake(&staker, Uint128::new(1_000)).unwrap();
+
+        let res: StakerResponse = app
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &app.staking,
+                &StakingQueryMsg::Staker {
+                    address: staker.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.balance, Uint128::new(1_000));
+    }
+}
@@ -0,0 +1,280 @@
+//! Collateral oracle mock: `native` sources are priced by the mock oracle, interchain query
+//! sources are not supported
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response,
+    StdError, StdResult,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::{Bound, Item, Map};
+use melange_protocol::{
+    collateral_oracle::{
+        CollateralAssetsResponse, CollateralFilter, CollateralInfoResponse,
+        CollateralInfosResponse, CollateralPriceResponse, CollateralPricesResponse,
+        CollateralStatusResponse, CollateralStatusesResponse, ExecuteMsg, QueryMsg, SourceType,
+    },
+    common::{PageRequest, PageResponse},
+    oracle::{PriceResponse, QueryMsg as OracleQueryMsg},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub oracle: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct Collateral {
+    price_source: SourceType,
+    multiplier: Decimal,
+    is_revoked: bool,
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+const ORACLE: Item<Addr> = Item::new("oracle");
+const COLLATERALS: Map<&str, Collateral> = Map::new("collateral");
+
+pub fn contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    ORACLE.save(deps.storage, &deps.api.addr_validate(&msg.oracle)?)?;
+    Ok(Response::new())
+}
+
+fn execute(deps: DepsMut, _env: Env, _info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::RegisterCollateralAsset {
+            asset,
+            price_source,
+            multiplier,
+        }
+        | ExecuteMsg::RegisterBridgedCollateralAsset {
+            asset,
+            price_source,
+            multiplier,
+            ..
+        } => COLLATERALS.save(
+            deps.storage,
+            &asset.to_string(),
+            &Collateral {
+                price_source,
+                multiplier,
+                is_revoked: false,
+            },
+        )?,
+        ExecuteMsg::RevokeCollateralAsset { asset } => {
+            COLLATERALS.update(deps.storage, &asset.to_string(), |collateral| {
+                let mut collateral = collateral.ok_or_else(not_registered)?;
+                collateral.is_revoked = true;
+                StdResult::Ok(collateral)
+            })?;
+        }
+        ExecuteMsg::UpdateCollateralPriceSource {
+            asset,
+            price_source,
+        } => {
+            COLLATERALS.update(deps.storage, &asset.to_string(), |collateral| {
+                let mut collateral = collateral.ok_or_else(not_registered)?;
+                collateral.price_source = price_source;
+                StdResult::Ok(collateral)
+            })?;
+        }
+        ExecuteMsg::UpdateCollateralMultiplier { asset, multiplier } => {
+            COLLATERALS.update(deps.storage, &asset.to_string(), |collateral| {
+                let mut collateral = collateral.ok_or_else(not_registered)?;
+                collateral.multiplier = multiplier;
+                StdResult::Ok(collateral)
+            })?;
+        }
+        _ => return Err(StdError::generic_err("Not supported by the mock")),
+    }
+
+    Ok(Response::new())
+}
+
+fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::CollateralPrice { asset, timeframe } => {
+            to_binary(&query_collateral_price(deps, asset, timeframe)?)
+        }
+        QueryMsg::CollateralAssetInfo { asset } => {
+            let collateral = COLLATERALS
+                .may_load(deps.storage, &asset)?
+                .ok_or_else(not_registered)?;
+            to_binary(&to_info_response(asset, collateral))
+        }
+        QueryMsg::CollateralAssetInfos {} => to_binary(&CollateralInfosResponse {
+            collaterals: COLLATERALS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    let (asset, collateral) = item?;
+                    Ok(to_info_response(asset, collateral))
+                })
+                .collect::<StdResult<Vec<_>>>()?,
+        }),
+        QueryMsg::CollateralAssets { filter, page } => to_binary(&query_collateral_assets(
+            deps,
+            filter.unwrap_or_default(),
+            page.unwrap_or_default(),
+        )?),
+        QueryMsg::Prices { assets, timeframe } => to_binary(&CollateralPricesResponse {
+            prices: assets
+                .into_iter()
+                .map(|asset| query_collateral_price(deps, asset.to_string(), timeframe))
+                .collect::<StdResult<Vec<_>>>()?,
+        }),
+        QueryMsg::CollateralInfos { assets } => to_binary(&CollateralStatusesResponse {
+            collaterals: assets
+                .into_iter()
+                .map(|asset| query_collateral_status(deps, &env, asset.to_string()))
+                .collect::<StdResult<Vec<_>>>()?,
+        }),
+        _ => Err(StdError::generic_err("Not supported by the mock")),
+    }
+}
+
+// unregistered assets are priced by the oracle with a multiplier of one
+fn query_collateral_price(
+    deps: Deps,
+    asset: String,
+    timeframe: Option<u64>,
+) -> StdResult<CollateralPriceResponse> {
+    let collateral = COLLATERALS
+        .may_load(deps.storage, &asset)?
+        .unwrap_or(Collateral {
+            price_source: SourceType::Native {
+                native_denom: asset.clone(),
+            },
+            multiplier: Decimal::one(),
+            is_revoked: false,
+        });
+
+    let (rate, last_updated) = match collateral.price_source {
+        SourceType::FixedPrice { price } => (price, u64::MAX),
+        SourceType::Native { native_denom } => {
+            let res: PriceResponse = deps.querier.query_wasm_smart(
+                ORACLE.load(deps.storage)?,
+                &OracleQueryMsg::Price {
+                    asset_token: native_denom,
+                    timeframe,
+                },
+            )?;
+            (res.rate, res.last_updated)
+        }
+        SourceType::InterchainQuery { .. } => {
+            return Err(StdError::generic_err("Not supported by the mock"))
+        }
+    };
+
+    Ok(CollateralPriceResponse {
+        asset,
+        rate,
+        last_updated,
+        multiplier: collateral.multiplier,
+        is_revoked: collateral.is_revoked,
+    })
+}
+
+fn query_collateral_assets(
+    deps: Deps,
+    filter: CollateralFilter,
+    page: PageRequest,
+) -> StdResult<CollateralAssetsResponse> {
+    let limit = page.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let cursor = page.parse_cursor::<String>()?;
+    let order = page.order.map_or(Order::Ascending, Order::from);
+    let bound = cursor.as_deref().map(Bound::exclusive);
+    let (min, max) = match order {
+        Order::Ascending => (bound, None),
+        Order::Descending => (None, bound),
+    };
+
+    let mut collaterals = vec![];
+    for item in COLLATERALS.range(deps.storage, min, max, order) {
+        let (asset, collateral) = item?;
+        if filter
+            .is_revoked
+            .is_some_and(|is_revoked| is_revoked != collateral.is_revoked)
+            || filter
+                .source_type
+                .as_ref()
+                .is_some_and(|source_type| *source_type != collateral.price_source.to_string())
+        {
+            continue;
+        }
+        if let Some(is_suspended) = filter.is_suspended {
+            if is_suspended != query_collateral_price(deps, asset.clone(), None).is_err() {
+                continue;
+            }
+        }
+
+        collaterals.push(to_info_response(asset, collateral));
+        if collaterals.len() == limit {
+            break;
+        }
+    }
+
+    let page = PageResponse::new(
+        collaterals
+            .last()
+            .map(|collateral| collateral.asset.clone()),
+        collaterals.len(),
+        limit,
+    );
+    Ok(CollateralAssetsResponse { collaterals, page })
+}
+
+// a failing price source suspends the asset instead of failing the batch
+fn query_collateral_status(
+    deps: Deps,
+    env: &Env,
+    asset: String,
+) -> StdResult<CollateralStatusResponse> {
+    Ok(match query_collateral_price(deps, asset.clone(), None) {
+        Ok(price) => CollateralStatusResponse {
+            asset,
+            rate: price.rate,
+            last_updated: price.last_updated,
+            age: env.block.time.seconds().saturating_sub(price.last_updated),
+            multiplier: price.multiplier,
+            is_revoked: price.is_revoked,
+            is_suspended: false,
+        },
+        Err(_) => {
+            let collateral = COLLATERALS.may_load(deps.storage, &asset)?;
+            CollateralStatusResponse {
+                asset,
+                rate: Decimal::zero(),
+                last_updated: 0,
+                age: 0,
+                multiplier: collateral
+                    .as_ref()
+                    .map_or(Decimal::one(), |collateral| collateral.multiplier),
+                is_revoked: collateral.is_some_and(|collateral| collateral.is_revoked),
+                is_suspended: true,
+            }
+        }
+    })
+}
+
+fn to_info_response(asset: String, collateral: Collateral) -> CollateralInfoResponse {
+    CollateralInfoResponse {
+        asset,
+        multiplier: collateral.multiplier,
+        source_type: collateral.price_source.to_string(),
+        is_revoked: collateral.is_revoked,
+    }
+}
+
+fn not_registered() -> StdError {
+    StdError::generic_err("Collateral asset not registered")
+}
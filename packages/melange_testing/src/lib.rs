@@ -0,0 +1,13 @@
+//! Preconfigured cw-multi-test `App` for end-to-end tests against the mint contract.
+//!
+//! [`MelangeApp`] instantiates mock oracle, collateral oracle, staking and lock contracts
+//! that implement just enough of the protocol interfaces for the mint contract to run, and
+//! wires them into a mint contract supplied by the caller.
+
+mod app;
+pub mod collateral_oracle;
+pub mod lock;
+pub mod oracle;
+pub mod staking;
+
+pub use app::{MelangeApp, OWNER};
@@ -0,0 +1,72 @@
+//! Price oracle mock: anyone can feed prices, feeder management and incentives are not
+//! supported
+use cosmwasm_std::{
+    to_binary, Binary, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError,
+    StdResult,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::Map;
+use melange_protocol::oracle::{ExecuteMsg, PriceResponse, QueryMsg};
+
+/// (price, block time it was fed at) by asset
+const PRICES: Map<&str, (Decimal, u64)> = Map::new("price");
+
+pub fn contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+fn instantiate(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn execute(deps: DepsMut, env: Env, _info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::FeedPrice { prices } => {
+            for (asset, price) in prices {
+                PRICES.save(deps.storage, &asset, &(price, env.block.time.seconds()))?;
+            }
+            Ok(Response::new())
+        }
+        ExecuteMsg::RegisterFeeder { .. }
+        | ExecuteMsg::ProposeFeeder { .. }
+        | ExecuteMsg::AcceptFeeder { .. }
+        | ExecuteMsg::UpdateIncentive { .. }
+        | ExecuteMsg::FundIncentive {}
+        | ExecuteMsg::ClaimFeederRewards {} => Err(unsupported()),
+    }
+}
+
+fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Price {
+            asset_token,
+            timeframe,
+        } => to_binary(&query_price(deps, &env, &asset_token, timeframe)?),
+        QueryMsg::Feeder { .. }
+        | QueryMsg::Feeders { .. }
+        | QueryMsg::Incentive {}
+        | QueryMsg::FeederRewards { .. } => Err(unsupported()),
+    }
+}
+
+fn unsupported() -> StdError {
+    StdError::generic_err("Feeders are not supported by the mock oracle")
+}
+
+fn query_price(
+    deps: Deps,
+    env: &Env,
+    asset: &str,
+    timeframe: Option<u64>,
+) -> StdResult<PriceResponse> {
+    let (rate, last_updated) = PRICES
+        .may_load(deps.storage, asset)?
+        .ok_or_else(|| StdError::generic_err(format!("No price for {}", asset)))?;
+    if let Some(timeframe) = timeframe {
+        if last_updated + timeframe < env.block.time.seconds() {
+            return Err(StdError::generic_err("Price is too old"));
+        }
+    }
+
+    Ok(PriceResponse { rate, last_updated })
+}
@@ -0,0 +1,43 @@
+//! Staking mock: stakes are set directly instead of bonding MEL
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Uint128,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::Map;
+use melange_protocol::staking::{QueryMsg, StakerResponse};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    SetStake { address: String, balance: Uint128 },
+}
+
+const STAKES: Map<&str, Uint128> = Map::new("stake");
+
+pub fn contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+fn instantiate(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn execute(deps: DepsMut, _env: Env, _info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::SetStake { address, balance } => {
+            STAKES.save(deps.storage, &address, &balance)?;
+            Ok(Response::new())
+        }
+    }
+}
+
+fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Staker { address } => to_binary(&StakerResponse {
+            balance: STAKES.may_load(deps.storage, &address)?.unwrap_or_default(),
+            address,
+        }),
+    }
+}